@@ -0,0 +1,366 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    grid::Coord,
+    json::Json,
+    level::{GameState, Level},
+    replay::REPLAY_DELTA_TIME,
+    tile_data::{tile_move::TileMove, tile_type::TileType},
+};
+
+/// Most ticks a single step command may run, so one client cannot stall the game.
+pub const MAX_STEP_TICKS: usize = 10_000;
+
+/// One line of the bot protocol, a JSON object with a `cmd` field:
+/// ```text
+/// {"cmd":"input","move":"right","slurp":false}  hold an input until the next input command
+/// {"cmd":"step","ticks":8}                      advance the level, one tile move is 8 ticks,
+///                                               at most MAX_STEP_TICKS at once
+/// {"cmd":"grid"}                                the tiles as rows of text level characters
+/// {"cmd":"state"}                               counters, game state and Murphy's position
+/// {"cmd":"load","level":3}                      start a level of the pack, counting from 1
+/// {"cmd":"restart"}                             start the current level again
+/// ```
+/// Every command gets a reply with `"ok":true` or `"ok":false` and an `error`, events noticed
+/// while stepping (`infotron`, `red_disk`, `died`, `finished`) are sent to every client.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BotCommand {
+    Input { mov: TileMove, slurping: bool },
+    Step { ticks: usize },
+    Grid,
+    State,
+    Load { level: usize },
+    Restart,
+}
+
+impl BotCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let json = Json::parse(line)?;
+        let cmd = json
+            .get("cmd")
+            .and_then(Json::as_str)
+            .ok_or("missing cmd")?;
+
+        match cmd {
+            "input" => {
+                let mov = match json.get("move").and_then(Json::as_str).unwrap_or("none") {
+                    "up" => TileMove::Up,
+                    "right" => TileMove::Right,
+                    "down" => TileMove::Down,
+                    "left" => TileMove::Left,
+                    "none" => TileMove::None,
+                    other => return Err(format!("unknown move: {other}")),
+                };
+                let slurping = json.get("slurp").and_then(Json::as_bool).unwrap_or(false);
+                Ok(BotCommand::Input { mov, slurping })
+            }
+            "step" => {
+                let ticks = json
+                    .get("ticks")
+                    .map_or(Some(1), Json::as_usize)
+                    .ok_or("ticks must be a whole number")?;
+                if ticks > MAX_STEP_TICKS {
+                    return Err(format!("ticks must be at most {MAX_STEP_TICKS}"));
+                }
+                Ok(BotCommand::Step { ticks })
+            }
+            "grid" => Ok(BotCommand::Grid),
+            "state" => Ok(BotCommand::State),
+            "load" => {
+                let level = json
+                    .get("level")
+                    .and_then(Json::as_usize)
+                    .filter(|level| *level > 0)
+                    .ok_or("level must be a number counting from 1")?;
+                Ok(BotCommand::Load { level: level - 1 })
+            }
+            "restart" => Ok(BotCommand::Restart),
+            other => Err(format!("unknown cmd: {other}")),
+        }
+    }
+}
+
+/// Runs bot commands against a level, shared by the headless and windowed frontends.
+#[derive(Default)]
+pub struct BotSession {
+    /// Index into the level set of the level being played.
+    pub level_index: usize,
+    /// Ticks since the level was loaded or restarted.
+    pub ticks: usize,
+}
+
+impl BotSession {
+    pub fn new(level_index: usize) -> Self {
+        Self {
+            level_index,
+            ticks: 0,
+        }
+    }
+
+    /// Runs `command` on `level`, returns the reply and pushes the events it caused.
+    pub fn run(
+        &mut self,
+        command: BotCommand,
+        level: &mut Level,
+        levels: &[Level],
+        events: &mut Vec<Json>,
+    ) -> Result<Json, String> {
+        match command {
+            BotCommand::Input { mov, slurping } => {
+                level.data.move_priority.clear();
+                if mov != TileMove::None {
+                    level.data.move_priority.push(mov);
+                }
+                level.data.slurping = slurping;
+            }
+            BotCommand::Step { ticks } => {
+                for _ in 0..ticks {
+                    let before = (
+                        level.data.infotrons_required,
+                        level.data.red_utility_disks,
+                        level.data.game_state,
+                    );
+                    level.tick(REPLAY_DELTA_TIME);
+                    self.ticks += 1;
+                    self.push_events(before, level, events);
+                }
+            }
+            BotCommand::Grid => {
+                let rows = level
+                    .grid
+                    .array
+                    .chunks(level.grid.width as usize)
+                    .map(|row| Json::String(row.iter().map(|tile| tile.typ.to_char()).collect()))
+                    .collect();
+                return Ok(Json::object([
+                    ("ok", true.into()),
+                    ("width", level.grid.width.into()),
+                    ("height", level.grid.height.into()),
+                    ("rows", Json::Array(rows)),
+                ]));
+            }
+            BotCommand::State => {
+                return Ok(Json::object([
+                    ("ok", true.into()),
+                    ("level", (self.level_index + 1).into()),
+                    ("tick", self.ticks.into()),
+                    ("game_state", game_state_name(level.data.game_state).into()),
+                    ("infotrons_required", level.data.infotrons_required.into()),
+                    ("red_disks", level.data.red_utility_disks.into()),
+                    ("gravity", level.data.gravity_enabled.into()),
+                    ("murphy", coord_json(level.grid.find(TileType::Murphy))),
+                ]));
+            }
+            BotCommand::Load { level: index } => {
+                let new_level = levels
+                    .get(index)
+                    .ok_or_else(|| format!("level must be between 1 and {}", levels.len()))?;
+                *level = new_level.clone();
+                self.level_index = index;
+                self.ticks = 0;
+            }
+            BotCommand::Restart => {
+                *level = levels[self.level_index].clone();
+                self.ticks = 0;
+            }
+        }
+
+        Ok(Json::object([("ok", true.into()), ("tick", self.ticks.into())]))
+    }
+
+    fn push_events(&self, before: (i32, u32, GameState), level: &Level, events: &mut Vec<Json>) {
+        let (infotrons, red_disks, game_state) = before;
+        let tick = Json::from(self.ticks);
+
+        if level.data.infotrons_required < infotrons {
+            events.push(Json::object([
+                ("event", "infotron".into()),
+                ("tick", tick.clone()),
+                ("infotrons_required", level.data.infotrons_required.into()),
+            ]));
+        }
+        if level.data.red_utility_disks > red_disks {
+            events.push(Json::object([
+                ("event", "red_disk".into()),
+                ("tick", tick.clone()),
+                ("red_disks", level.data.red_utility_disks.into()),
+            ]));
+        }
+        if level.data.game_state != game_state {
+            events.push(Json::object([
+                ("event", game_state_name(level.data.game_state).into()),
+                ("tick", tick),
+            ]));
+        }
+    }
+}
+
+fn game_state_name(game_state: GameState) -> &'static str {
+    match game_state {
+        GameState::Active => "active",
+        GameState::Died => "died",
+        GameState::Finished => "finished",
+    }
+}
+
+fn coord_json(coord: Option<Coord>) -> Json {
+    match coord {
+        Some(coord) => Json::Array(vec![coord.x.into(), coord.y.into()]),
+        None => Json::Null,
+    }
+}
+
+type ClientWriters = Arc<Mutex<Vec<(usize, Box<dyn Write + Send>)>>>;
+
+/// Accepts clients on a local socket and hands their commands to the game, replies and
+/// events are written back as JSON lines.
+pub struct BotServer {
+    commands: Receiver<(usize, Result<BotCommand, String>)>,
+    clients: ClientWriters,
+}
+
+impl BotServer {
+    /// Listens on a Unix socket when `address` is a path, otherwise on TCP, like
+    /// `127.0.0.1:7777`.
+    pub fn listen(address: &str) -> io::Result<Self> {
+        let (sender, commands) = mpsc::channel();
+        let clients = ClientWriters::default();
+
+        #[cfg(unix)]
+        if address.contains('/') {
+            use std::os::unix::fs::FileTypeExt;
+
+            // a socket left behind by an earlier run is replaced, anything else is not ours
+            match std::fs::symlink_metadata(address) {
+                Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(address)?,
+                Ok(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        "exists and is not a socket",
+                    ))
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+            let listener = std::os::unix::net::UnixListener::bind(address)?;
+            let accepted_clients = clients.clone();
+            thread::spawn(move || {
+                for (id, stream) in listener.incoming().flatten().enumerate() {
+                    if let Ok(writer) = stream.try_clone() {
+                        add_client(id, stream, Box::new(writer), &accepted_clients, &sender);
+                    }
+                }
+            });
+            return Ok(Self { commands, clients });
+        }
+
+        let listener = TcpListener::bind(address)?;
+        let accepted_clients = clients.clone();
+        thread::spawn(move || {
+            for (id, stream) in listener.incoming().flatten().enumerate() {
+                if let Ok(writer) = stream.try_clone() {
+                    add_client(id, stream, Box::new(writer), &accepted_clients, &sender);
+                }
+            }
+        });
+        Ok(Self { commands, clients })
+    }
+
+    /// The next command if one has arrived, for frontends that cannot wait.
+    pub fn try_recv(&self) -> Option<(usize, Result<BotCommand, String>)> {
+        self.commands.try_recv().ok()
+    }
+
+    /// Waits for the next command.
+    pub fn recv(&self) -> Option<(usize, Result<BotCommand, String>)> {
+        self.commands.recv().ok()
+    }
+
+    pub fn reply(&self, client: usize, message: &Json) {
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain_mut(|(id, writer)| *id != client || writeln!(writer, "{message}").is_ok());
+    }
+
+    pub fn broadcast(&self, message: &Json) {
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain_mut(|(_, writer)| writeln!(writer, "{message}").is_ok());
+    }
+
+    /// Replies to `client` with the result of a command and sends its events to everyone.
+    pub fn respond(&self, client: usize, result: Result<Json, String>, events: &[Json]) {
+        let reply = result.unwrap_or_else(|err| {
+            Json::object([("ok", false.into()), ("error", err.into())])
+        });
+        self.reply(client, &reply);
+        for event in events {
+            self.broadcast(event);
+        }
+    }
+}
+
+fn add_client(
+    id: usize,
+    stream: impl Read + Send + 'static,
+    writer: Box<dyn Write + Send>,
+    clients: &ClientWriters,
+    sender: &Sender<(usize, Result<BotCommand, String>)>,
+) {
+    clients.lock().unwrap().push((id, writer));
+    let sender = sender.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if sender.send((id, BotCommand::parse(&line))).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed_with_their_defaults() {
+        let parse = |line| BotCommand::parse(line).unwrap();
+        assert_eq!(
+            parse(r#"{"cmd":"input","move":"left","slurp":true}"#),
+            BotCommand::Input { mov: TileMove::Left, slurping: true }
+        );
+        assert_eq!(
+            parse(r#"{"cmd":"input"}"#),
+            BotCommand::Input { mov: TileMove::None, slurping: false }
+        );
+        assert_eq!(parse(r#"{"cmd":"step"}"#), BotCommand::Step { ticks: 1 });
+        assert_eq!(parse(r#"{"cmd":"load","level":3}"#), BotCommand::Load { level: 2 });
+        assert_eq!(parse(r#"{"cmd":"restart"}"#), BotCommand::Restart);
+    }
+
+    #[test]
+    fn bad_commands_are_refused() {
+        let error = |line| BotCommand::parse(line).unwrap_err();
+        assert_eq!(error(r#"{"ticks":8}"#), "missing cmd");
+        assert_eq!(error(r#"{"cmd":"fly"}"#), "unknown cmd: fly");
+        assert_eq!(error(r#"{"cmd":"input","move":"back"}"#), "unknown move: back");
+        assert_eq!(error(r#"{"cmd":"step","ticks":1.5}"#), "ticks must be a whole number");
+        assert_eq!(error(r#"{"cmd":"load","level":0}"#), "level must be a number counting from 1");
+        assert_eq!(
+            error(&format!(r#"{{"cmd":"step","ticks":{}}}"#, MAX_STEP_TICKS + 1)),
+            format!("ticks must be at most {MAX_STEP_TICKS}")
+        );
+    }
+}
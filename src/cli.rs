@@ -0,0 +1,233 @@
+use supaplex::{
+    grid::Coord,
+    level::{ExitRule, StartOverride},
+};
+
+/// Sprite sheets shipped in `assets/`, `--sprites` also accepts any other path.
+pub const SPRITE_SHEETS: [&str; 4] = ["moving", "moving2", "moving3", "tiles"];
+
+pub const USAGE: &str = "\
+usage:
+  supaplex [options]
+  supaplex --headless [options]
+  supaplex --tui [options]
+  supaplex --validate <levels.dat>
+  supaplex --solve <levels.dat> <level> [replay]
+  supaplex --export-png <levels.dat> <level> <output.png> [sprites.png]
+
+options:
+  --levels <file>      levels file to play, DAT, SP, MPX or text
+  --level <n>          level to start, counting from 1
+  --replay <file>      play back a replay, its level is used unless --level is given
+  --ticks <n>          with --headless, ticks to run, defaults to the replay length
+  --dump <file>        with --headless, write the final state here instead of stdout
+  --listen <address>   let bots play through JSON lines on a TCP address or Unix socket path
+  --host <address>     wait for a second player to join over TCP, like 0.0.0.0:7000
+  --join <address>     join a player hosting on this address, with the same level loaded
+  --coop               play levels with two Murphys together, arrows and Space for the first,
+                       WASD and Q for the second, or share the level with --host
+  --exit <all|any>     with --coop, whether every Murphy or any one must reach the exit
+  --click-move         walk Murphy to the tile clicked, M toggles this while playing
+  --scale <n>          window scale, sets the window size and zoom
+  --sprites <sheet>    moving, moving2, moving3, tiles or a path to a sprite sheet
+  --murphy <x>,<y>     start with Murphy moved to this tile
+  --infotrons <n>      start with this many infotrons required
+  --red-disks <n>      start with this many red utility disks
+  --gravity <on|off>   start with gravity enabled or disabled";
+
+pub enum Command {
+    Play(PlayArgs),
+    Headless(PlayArgs),
+    Tui(PlayArgs),
+    Validate {
+        path: String,
+    },
+    Solve {
+        path: String,
+        level: usize,
+        output: Option<String>,
+    },
+    ExportPng {
+        path: String,
+        level: usize,
+        output: String,
+        sprites: Option<String>,
+    },
+}
+
+#[derive(Default)]
+pub struct PlayArgs {
+    pub levels: Option<String>,
+    /// Index into the level set, counting from 0.
+    pub level: Option<usize>,
+    pub start: Option<StartOverride>,
+    pub replay: Option<String>,
+    pub ticks: Option<usize>,
+    pub dump: Option<String>,
+    pub scale: Option<u32>,
+    pub sprites: Option<String>,
+    /// Address of the bot control server, `host:port` or a Unix socket path.
+    pub listen: Option<String>,
+    pub host: Option<String>,
+    pub join: Option<String>,
+    pub coop: bool,
+    pub exit_rule: Option<ExitRule>,
+    pub click_move: bool,
+}
+
+impl PlayArgs {
+    /// How co-op levels are won, `None` when not playing co-op.
+    pub fn coop_exit_rule(&self) -> Option<ExitRule> {
+        self.coop
+            .then(|| self.exit_rule.unwrap_or(ExitRule::All))
+    }
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let args: Vec<String> = args.into_iter().collect();
+
+    match args.first().map(String::as_str) {
+        Some("--validate") => {
+            let [_, path] = args.as_slice() else {
+                return Err("--validate takes a levels file".to_string());
+            };
+            return Ok(Command::Validate { path: path.clone() });
+        }
+        Some("--solve") => {
+            let (path, level, output) = match args.as_slice() {
+                [_, path, level] => (path, level, None),
+                [_, path, level, output] => (path, level, Some(output.clone())),
+                _ => return Err("--solve takes a levels file, a level and an optional replay file".to_string()),
+            };
+            return Ok(Command::Solve {
+                path: path.clone(),
+                level: parse_level_number(level)?,
+                output,
+            });
+        }
+        Some("--export-png") => {
+            let (path, level, output, sprites) = match args.as_slice() {
+                [_, path, level, output] => (path, level, output, None),
+                [_, path, level, output, sprites] => (path, level, output, Some(sprites.clone())),
+                _ => return Err("--export-png takes a levels file, a level, an output file and an optional sprite sheet".to_string()),
+            };
+            return Ok(Command::ExportPng {
+                path: path.clone(),
+                level: parse_level_number(level)?,
+                output: output.clone(),
+                sprites,
+            });
+        }
+        Some("--tui") => {
+            let play = parse_play_args(args.into_iter().skip(1))?;
+            if play.listen.is_some() || play.host.is_some() || play.join.is_some() || play.coop {
+                return Err("--tui plays alone, without --listen, --host, --join or --coop".to_string());
+            }
+            return Ok(Command::Tui(play));
+        }
+        Some("--headless") => {
+            let play = parse_play_args(args.into_iter().skip(1))?;
+            if play.coop && play.host.is_none() {
+                return Err("--headless --coop needs --host, local co-op needs a window".to_string());
+            }
+            return Ok(Command::Headless(play));
+        }
+        _ => {}
+    }
+
+    parse_play_args(args).map(Command::Play)
+}
+
+fn parse_play_args(args: impl IntoIterator<Item = String>) -> Result<PlayArgs, String> {
+    let mut play = PlayArgs::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--levels" => play.levels = Some(value()?),
+            "--level" => play.level = Some(parse_level_number(&value()?)?),
+            "--replay" => play.replay = Some(value()?),
+            "--ticks" => play.ticks = Some(parse_number(&value()?)?),
+            "--dump" => play.dump = Some(value()?),
+            "--scale" => {
+                let scale = parse_number(&value()?)?;
+                if scale == 0 {
+                    return Err("scale must be at least 1".to_string());
+                }
+                play.scale = Some(scale);
+            }
+            "--sprites" => play.sprites = Some(sprite_sheet_path(&value()?)),
+            "--listen" => play.listen = Some(value()?),
+            "--host" => play.host = Some(value()?),
+            "--join" => play.join = Some(value()?),
+            "--coop" => play.coop = true,
+            "--click-move" => play.click_move = true,
+            "--exit" => {
+                play.exit_rule = match value()?.as_str() {
+                    "all" => Some(ExitRule::All),
+                    "any" => Some(ExitRule::Any),
+                    other => return Err(format!("bad exit rule: {other}")),
+                };
+            }
+            "--murphy" => {
+                let value = value()?;
+                let (x, y) = value
+                    .split_once(',')
+                    .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+                    .ok_or_else(|| format!("bad tile: {value}"))?;
+                start(&mut play).murphy = Some(Coord::new(x, y));
+            }
+            "--infotrons" => {
+                start(&mut play).infotrons_required = Some(parse_number(&value()?)?);
+            }
+            "--red-disks" => {
+                start(&mut play).red_utility_disks = Some(parse_number(&value()?)?);
+            }
+            "--gravity" => {
+                let gravity = match value()?.as_str() {
+                    "on" => true,
+                    "off" => false,
+                    other => return Err(format!("bad gravity: {other}")),
+                };
+                start(&mut play).gravity_enabled = Some(gravity);
+            }
+            other => return Err(format!("unknown option: {other}")),
+        }
+    }
+
+    if play.host.is_some() && play.join.is_some() {
+        return Err("--host and --join cannot be used together".to_string());
+    }
+    if play.coop && play.join.is_some() {
+        return Err("--coop cannot be used with --join, the host chooses the mode".to_string());
+    }
+    if play.exit_rule.is_some() && !play.coop {
+        return Err("--exit needs --coop".to_string());
+    }
+
+    Ok(play)
+}
+
+fn sprite_sheet_path(value: &str) -> String {
+    let name = value.strip_suffix(".png").unwrap_or(value);
+    if SPRITE_SHEETS.contains(&name) {
+        format!("assets/{name}.png")
+    } else {
+        value.to_string()
+    }
+}
+
+fn start(play: &mut PlayArgs) -> &mut StartOverride {
+    play.start.get_or_insert_with(StartOverride::default)
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("bad number: {value}"))
+}
+
+fn parse_level_number(value: &str) -> Result<usize, String> {
+    match parse_number::<usize>(value)? {
+        0 => Err("levels are counted from 1".to_string()),
+        level => Ok(level - 1),
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs;
+
+use macroquad::{
+    prelude::{get_char_pressed, is_key_pressed, Color, KeyCode},
+    shapes::draw_rectangle,
+    text::{draw_text_ex, TextParams},
+    window::{screen_height, screen_width},
+};
+
+use supaplex::{
+    bot::MAX_STEP_TICKS,
+    grid::Coord,
+    level::Level,
+    level_loader::level_to_text,
+    replay::{ReplayStep, REPLAY_DELTA_TIME},
+    tile_data::{tile_move::TileMove, tile_type::TileType},
+};
+
+const HELP: &str = "\
+set <x>,<y> [tile]   place a tile, a text level character, empty without one
+murphy <x>,<y>       move Murphy there
+infotrons <n>        set the infotrons still required
+gravity [on|off]     switch gravity, toggles without a value
+step [n]             run n ticks without input, 1 by default
+dump [file]          write the level as text, to stdout without a file";
+
+const LOG_LINES: usize = 8;
+const MAX_INPUT_LEN: usize = 80;
+
+/// Debug commands typed in while the game waits, opened with the ` key in debug mode.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    log: Vec<String>,
+}
+
+impl Console {
+    /// Returns true when a command that can change the level ran.
+    pub fn update(&mut self, level: &mut Level) -> bool {
+        // the queue hands out the last character typed first
+        let chars: Vec<char> = std::iter::from_fn(get_char_pressed).collect();
+        if is_key_pressed(KeyCode::Escape) {
+            self.open = false;
+            return false;
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.input.pop();
+        }
+        for c in chars.into_iter().rev() {
+            if (c.is_ascii_graphic() || c == ' ') && c != '`' && self.input.len() < MAX_INPUT_LEN {
+                self.input.push(c);
+            }
+        }
+
+        if !is_key_pressed(KeyCode::Enter) {
+            return false;
+        }
+        let line = std::mem::take(&mut self.input);
+        self.log.push(format!("> {line}"));
+        match run_command(&line, level) {
+            Ok(output) => self.log.extend(output.lines().map(str::to_string)),
+            Err(err) => self.log.push(format!("error: {err}")),
+        }
+        let command = line.split_whitespace().next().unwrap_or("");
+        !matches!(command, "" | "help" | "dump")
+    }
+
+    pub fn draw(&self, text_params: TextParams) {
+        let top = screen_height() - (LOG_LINES + 2) as f32 * 20.0;
+        draw_rectangle(0.0, top, screen_width(), screen_height() - top, Color::new(0.0, 0.0, 0.0, 0.75));
+        let start = self.log.len().saturating_sub(LOG_LINES);
+        for (i, line) in self.log[start..].iter().enumerate() {
+            draw_text_ex(line, 10.0, top + 20.0 + i as f32 * 20.0, text_params);
+        }
+        let prompt = format!("> {}_", self.input);
+        draw_text_ex(&prompt, 10.0, screen_height() - 15.0, text_params);
+    }
+}
+
+/// Runs one console line against the level, the result is what to show.
+pub fn run_command(line: &str, level: &mut Level) -> Result<String, String> {
+    let (command, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+    match command {
+        "" => Ok(String::new()),
+        "help" => Ok(HELP.to_string()),
+        "set" => {
+            let (coord, tile) = args.split_once(' ').unwrap_or((args, ""));
+            let coord = parse_coord(coord, level)?;
+            let typ = match tile.chars().collect::<Vec<char>>().as_slice() {
+                [] => TileType::Empty,
+                [c] => TileType::from_char(*c).ok_or_else(|| format!("unknown tile: {c}"))?,
+                _ => return Err(format!("bad tile: {tile}")),
+            };
+            level.grid.set(&coord, typ.to_tile());
+            Ok(format!("{typ:?} at {},{}", coord.x, coord.y))
+        }
+        "murphy" => {
+            if !level.data.players.is_empty() {
+                return Err("Murphy cannot be moved in co-op".to_string());
+            }
+            let coord = parse_coord(args, level)?;
+            level.move_murphy(coord);
+            Ok(format!("Murphy at {},{}", coord.x, coord.y))
+        }
+        "infotrons" => {
+            level.data.infotrons_required = parse_number(args)?;
+            Ok(format!("{} infotrons required", level.data.infotrons_required))
+        }
+        "gravity" => {
+            level.data.gravity_enabled = match args.trim() {
+                "" => !level.data.gravity_enabled,
+                "on" => true,
+                "off" => false,
+                other => return Err(format!("bad gravity: {other}")),
+            };
+            let on_off = if level.data.gravity_enabled { "on" } else { "off" };
+            Ok(format!("gravity {on_off}"))
+        }
+        "step" => {
+            let ticks = match args.trim() {
+                "" => 1,
+                ticks => parse_number(ticks)?,
+            };
+            if ticks > MAX_STEP_TICKS {
+                return Err(format!("at most {MAX_STEP_TICKS} ticks at once"));
+            }
+            ReplayStep::new(TileMove::None).play(level, REPLAY_DELTA_TIME, ticks);
+            Ok(format!("stepped {ticks} ticks"))
+        }
+        "dump" => {
+            let text = level_to_text(level);
+            match args.trim() {
+                "" => {
+                    print!("{text}");
+                    Ok("level written to stdout".to_string())
+                }
+                path => {
+                    fs::write(path, text).map_err(|err| format!("{path}: {err}"))?;
+                    Ok(format!("level written to {path}"))
+                }
+            }
+        }
+        other => Err(format!("unknown command: {other}, try help")),
+    }
+}
+
+fn parse_coord(value: &str, level: &Level) -> Result<Coord, String> {
+    let coord = value
+        .split_once(',')
+        .and_then(|(x, y)| Some(Coord::new(x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        .ok_or_else(|| format!("bad tile: {value}"))?;
+    if !level.grid.contains(&coord) {
+        return Err(format!("{},{} is outside the level", coord.x, coord.y));
+    }
+    Ok(coord)
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("bad number: {value}"))
+}
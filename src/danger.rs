@@ -0,0 +1,35 @@
+use crate::{
+    grid::Coord,
+    level::Level,
+    replay::{ReplayStep, REPLAY_DELTA_TIME, REPLAY_TICKS_PER_STEP},
+    tile_data::{tile_move::TileMove, tile_state::TileState},
+};
+
+/// How far `predict_danger` looks ahead, two moves of Murphy.
+pub const DANGER_TICKS: usize = 2 * REPLAY_TICKS_PER_STEP;
+
+/// The tiles something dangerous will pass over in the next `ticks` fixed ticks if nobody
+/// touches the keys: falling zonks and infotrons, explosions from disks and the paths of
+/// enemies. The level is played ahead on a copy, `level` itself does not change.
+pub fn predict_danger(level: &Level, ticks: usize) -> Vec<Coord> {
+    let mut future = level.clone();
+    let idle = ReplayStep::new(TileMove::None);
+    idle.apply(&mut future);
+    for player in &mut future.data.players {
+        idle.apply_to_player(player);
+    }
+
+    let mut hit = vec![false; future.grid.array.len()];
+    for _ in 0..ticks {
+        future.tick(REPLAY_DELTA_TIME);
+        for (i, tile) in future.grid.array.iter().enumerate() {
+            hit[i] |= tile.state == TileState::Dangerous;
+        }
+    }
+
+    hit.iter()
+        .enumerate()
+        .filter(|(_, hit)| **hit)
+        .map(|(i, _)| future.grid.coord(i))
+        .collect()
+}
@@ -0,0 +1,424 @@
+use macroquad::{
+    prelude::{
+        get_char_pressed, is_key_down, is_key_pressed, is_mouse_button_down,
+        is_mouse_button_pressed, is_mouse_button_released, mouse_position, vec2, Color, KeyCode,
+        MouseButton, Rect, Vec2, DARKGRAY, RED, WHITE, YELLOW,
+    },
+    shapes::{draw_rectangle, draw_rectangle_lines},
+    text::{draw_text_ex, TextParams},
+    texture::{draw_texture_ex, DrawTextureParams, Texture2D},
+    window::{screen_height, screen_width},
+};
+
+use supaplex::{
+    grid::{Coord, Grid},
+    level::{GameState, Level, SpecialPort},
+    tile_data::{tile_move::TileMove, tile_type::TileType},
+};
+
+use crate::pixel_camera::PixelCamera;
+
+const UNDO_LIMIT: usize = 100;
+const MAX_SPECIAL_PORTS: usize = 10;
+const MAX_NAME_LEN: usize = 23;
+const PALETTE_TILE_SIZE: f32 = 32.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EditorTool {
+    Pencil,
+    Rectangle,
+    FloodFill,
+    SpecialPort,
+}
+
+pub enum EditorAction {
+    None,
+    Playtest,
+    Save,
+    Exit,
+}
+
+pub struct Editor {
+    pub level: Level,
+    pub playtesting: bool,
+    tool: EditorTool,
+    palette_index: usize,
+    hovered: Option<Coord>,
+    drag_start: Option<Coord>,
+    editing_name: bool,
+    undo_stack: Vec<Level>,
+    redo_stack: Vec<Level>,
+}
+
+impl Editor {
+    pub fn new(level: Level) -> Self {
+        Self {
+            level,
+            playtesting: false,
+            tool: EditorTool::Pencil,
+            palette_index: 2,
+            hovered: None,
+            drag_start: None,
+            editing_name: false,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+
+    pub fn selected(&self) -> TileType {
+        TileType::PALETTE[self.palette_index]
+    }
+
+    pub fn update(&mut self, camera: &mut PixelCamera) -> EditorAction {
+        let chars: Vec<char> = std::iter::from_fn(get_char_pressed).collect();
+
+        if self.editing_name {
+            self.edit_name(chars.into_iter().rev());
+            return EditorAction::None;
+        }
+
+        camera.move_camera_with_keys(KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D);
+        camera.handle_zoom();
+
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        if ctrl {
+            if is_key_pressed(KeyCode::Z) {
+                self.undo();
+            }
+            if is_key_pressed(KeyCode::Y) {
+                self.redo();
+            }
+            if is_key_pressed(KeyCode::S) {
+                return EditorAction::Save;
+            }
+            return EditorAction::None;
+        }
+
+        if is_key_pressed(KeyCode::Enter) {
+            return EditorAction::Playtest;
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            return EditorAction::Exit;
+        }
+
+        self.update_tools();
+        self.update_header();
+
+        let mouse = Vec2::from(mouse_position());
+        if mouse.y >= self.palette_top() {
+            self.hovered = None;
+            if is_mouse_button_pressed(MouseButton::Left) {
+                if let Some(index) = self.palette_index_at(mouse) {
+                    self.palette_index = index;
+                }
+            }
+            return EditorAction::None;
+        }
+
+        let world = camera.screen_to_world(mouse) / 16.0;
+        let coord = Coord::new(world.x.floor() as i16, world.y.floor() as i16);
+        self.hovered = self.in_bounds(&coord).then_some(coord);
+        let Some(coord) = self.hovered else {
+            return EditorAction::None;
+        };
+
+        if is_mouse_button_pressed(MouseButton::Right) {
+            let typ = self.level.grid.get(&coord).typ;
+            if let Some(index) = TileType::PALETTE.iter().position(|t| *t == typ) {
+                self.palette_index = index;
+            }
+        }
+
+        match self.tool {
+            EditorTool::Pencil => {
+                if is_mouse_button_pressed(MouseButton::Left) {
+                    self.push_undo();
+                }
+                if is_mouse_button_down(MouseButton::Left) {
+                    self.paint(&coord, self.selected());
+                }
+            }
+            EditorTool::Rectangle => {
+                if is_mouse_button_pressed(MouseButton::Left) {
+                    self.drag_start = Some(coord);
+                }
+                if is_mouse_button_released(MouseButton::Left) {
+                    if let Some(start) = self.drag_start.take() {
+                        self.push_undo();
+                        self.fill_rect(&start, &coord, self.selected());
+                    }
+                }
+            }
+            EditorTool::FloodFill => {
+                if is_mouse_button_pressed(MouseButton::Left) {
+                    self.push_undo();
+                    self.flood_fill(&coord, self.selected());
+                }
+            }
+            EditorTool::SpecialPort => {
+                if is_mouse_button_pressed(MouseButton::Left) {
+                    self.push_undo();
+                    self.toggle_special_port(&coord);
+                }
+            }
+        }
+
+        EditorAction::None
+    }
+
+    fn update_tools(&mut self) {
+        if is_key_pressed(KeyCode::Key1) {
+            self.tool = EditorTool::Pencil;
+        }
+        if is_key_pressed(KeyCode::Key2) {
+            self.tool = EditorTool::Rectangle;
+        }
+        if is_key_pressed(KeyCode::Key3) {
+            self.tool = EditorTool::FloodFill;
+        }
+        if is_key_pressed(KeyCode::Key4) {
+            self.tool = EditorTool::SpecialPort;
+        }
+        if is_key_pressed(KeyCode::LeftBracket) {
+            self.palette_index =
+                (self.palette_index + TileType::PALETTE.len() - 1) % TileType::PALETTE.len();
+        }
+        if is_key_pressed(KeyCode::RightBracket) {
+            self.palette_index = (self.palette_index + 1) % TileType::PALETTE.len();
+        }
+    }
+
+    /// G, F and X toggle gravity, frozen zonks and frozen enemies of the hovered special port
+    /// when using the special port tool, and of the level itself otherwise.
+    fn update_header(&mut self) {
+        let toggle_gravity = is_key_pressed(KeyCode::G);
+        let toggle_zonks = is_key_pressed(KeyCode::F);
+        let toggle_enemies = is_key_pressed(KeyCode::X);
+        if !(toggle_gravity || toggle_zonks || toggle_enemies) {
+            if is_key_pressed(KeyCode::N) {
+                self.editing_name = true;
+            }
+            let infotrons =
+                is_key_pressed(KeyCode::PageUp) as i32 - is_key_pressed(KeyCode::PageDown) as i32;
+            if infotrons != 0 {
+                self.push_undo();
+                self.level.data.infotrons_required =
+                    (self.level.data.infotrons_required + infotrons).clamp(0, 255);
+            }
+            return;
+        }
+
+        self.push_undo();
+        let hovered = self.hovered;
+        let port = self
+            .level
+            .data
+            .special_ports
+            .iter_mut()
+            .find(|port| Some(port.coord) == hovered);
+        match (self.tool, port) {
+            (EditorTool::SpecialPort, Some(port)) => {
+                port.gravity_enabled ^= toggle_gravity;
+                port.zonks_frozen ^= toggle_zonks;
+                port.enemies_frozen ^= toggle_enemies;
+            }
+            _ => {
+                self.level.data.gravity_enabled ^= toggle_gravity;
+                self.level.data.zonks_frozen ^= toggle_zonks;
+            }
+        }
+    }
+
+    fn edit_name(&mut self, chars: impl Iterator<Item = char>) {
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::Escape) {
+            self.editing_name = false;
+            return;
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.level.data.name.pop();
+        }
+        for c in chars {
+            if (c.is_ascii_graphic() || c == ' ') && self.level.data.name.len() < MAX_NAME_LEN {
+                self.level.data.name.push(c.to_ascii_uppercase());
+            }
+        }
+    }
+
+    fn push_undo(&mut self) {
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(self.level.clone());
+        self.redo_stack.clear();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(level) = self.undo_stack.pop() {
+            self.redo_stack.push(std::mem::replace(&mut self.level, level));
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(level) = self.redo_stack.pop() {
+            self.undo_stack.push(std::mem::replace(&mut self.level, level));
+        }
+    }
+
+    fn in_bounds(&self, coord: &Coord) -> bool {
+        self.level.grid.contains(coord)
+    }
+
+    pub fn paint(&mut self, coord: &Coord, typ: TileType) {
+        if self.in_bounds(coord) && self.level.grid.get(coord).typ != typ {
+            self.level.grid.set(coord, typ.to_tile());
+        }
+    }
+
+    pub fn fill_rect(&mut self, a: &Coord, b: &Coord, typ: TileType) {
+        let min = Coord::new(a.x.min(b.x), a.y.min(b.y));
+        let max = Coord::new(a.x.max(b.x), a.y.max(b.y));
+        for coord in self.level.grid.region(min, max) {
+            self.paint(&coord, typ);
+        }
+    }
+
+    pub fn flood_fill(&mut self, start: &Coord, typ: TileType) {
+        let target = self.level.grid.get(start).typ;
+        if target == typ {
+            return;
+        }
+
+        let same = |grid: &Grid, from: &Coord, mov: TileMove| {
+            let to = from.offset(&mov);
+            (grid.get(&to).typ == target).then_some(to)
+        };
+        for coord in self.level.grid.flood_fill(*start, same) {
+            self.paint(&coord, typ);
+        }
+    }
+
+    pub fn toggle_special_port(&mut self, coord: &Coord) {
+        let ports = &mut self.level.data.special_ports;
+        if let Some(i) = ports.iter().position(|port| port.coord == *coord) {
+            ports.remove(i);
+        } else if ports.len() < MAX_SPECIAL_PORTS && self.level.grid.get(coord).typ.is_port() {
+            ports.push(SpecialPort {
+                coord: *coord,
+                gravity_enabled: true,
+                zonks_frozen: false,
+                enemies_frozen: false,
+            });
+        }
+    }
+
+    /// The level as it would be played, without any leftover state from previous playtests.
+    pub fn playtest_level(&self) -> Level {
+        let mut level = self.level.clone();
+        level.data.game_state = GameState::Active;
+        level.data.move_priority.clear();
+        level
+    }
+
+    fn palette_columns(&self) -> usize {
+        ((screen_width() / PALETTE_TILE_SIZE) as usize).max(1)
+    }
+
+    fn palette_top(&self) -> f32 {
+        let rows = TileType::PALETTE.len().div_ceil(self.palette_columns());
+        screen_height() - rows as f32 * PALETTE_TILE_SIZE
+    }
+
+    fn palette_index_at(&self, mouse: Vec2) -> Option<usize> {
+        let column = (mouse.x / PALETTE_TILE_SIZE) as usize;
+        let row = ((mouse.y - self.palette_top()) / PALETTE_TILE_SIZE) as usize;
+        let index = row * self.palette_columns() + column;
+        (column < self.palette_columns() && index < TileType::PALETTE.len()).then_some(index)
+    }
+
+    pub fn draw(&self, texture: Texture2D) {
+        self.level.draw(texture);
+
+        for port in &self.level.data.special_ports {
+            draw_tile_outline(&port.coord, YELLOW);
+        }
+
+        if let Some(hovered) = self.hovered {
+            match (self.tool, self.drag_start) {
+                (EditorTool::Rectangle, Some(start)) => {
+                    let min = vec2(start.x.min(hovered.x) as f32, start.y.min(hovered.y) as f32);
+                    let max = vec2(start.x.max(hovered.x) as f32, start.y.max(hovered.y) as f32);
+                    let size = (max - min + 1.0) * 16.0;
+                    draw_rectangle_lines(min.x * 16.0, min.y * 16.0, size.x, size.y, 1.0, RED);
+                }
+                _ => draw_tile_outline(&hovered, WHITE),
+            }
+        }
+    }
+
+    pub fn draw_ui(&self, text_params: TextParams, texture: Texture2D) {
+        let top = self.palette_top();
+        draw_rectangle(0.0, top, screen_width(), screen_height() - top, DARKGRAY);
+        for (i, typ) in TileType::PALETTE.iter().enumerate() {
+            let x = (i % self.palette_columns()) as f32 * PALETTE_TILE_SIZE;
+            let y = top + (i / self.palette_columns()) as f32 * PALETTE_TILE_SIZE;
+            let pos = typ.def_tex_pos();
+            draw_texture_ex(
+                texture,
+                x,
+                y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(Vec2::splat(PALETTE_TILE_SIZE)),
+                    source: Some(Rect::new(pos.x as f32 * 17.0, pos.y as f32 * 17.0, 16.0, 16.0)),
+                    ..Default::default()
+                },
+            );
+            if i == self.palette_index {
+                draw_rectangle_lines(x, y, PALETTE_TILE_SIZE, PALETTE_TILE_SIZE, 2.0, YELLOW);
+            }
+        }
+
+        let data = &self.level.data;
+        let name = if self.editing_name {
+            format!("name: {}_", data.name)
+        } else {
+            format!("name: {}", data.name)
+        };
+        let lines = [
+            format!("EDITOR  tool: {:?}  tile: {:?}", self.tool, self.selected()),
+            name,
+            format!(
+                "infotrons: {}  gravity: {}  frozen zonks: {}  special ports: {}",
+                data.infotrons_required,
+                data.gravity_enabled,
+                data.zonks_frozen,
+                data.special_ports.len()
+            ),
+            "1-4 tools  [ ] tile  RMB pick  N name  G/F/X flags  PgUp/PgDn infotrons".to_string(),
+            "Ctrl+Z/Y undo/redo  Ctrl+S save  Enter playtest  Esc exit".to_string(),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            draw_text_ex(line, 10.0, 60.0 + i as f32 * 20.0, text_params);
+        }
+
+        if let Some(port) = data
+            .special_ports
+            .iter()
+            .find(|port| Some(port.coord) == self.hovered)
+        {
+            draw_text_ex(
+                &format!(
+                    "special port  gravity: {}  frozen zonks: {}  frozen enemies: {}",
+                    port.gravity_enabled, port.zonks_frozen, port.enemies_frozen
+                ),
+                10.0,
+                top - 10.0,
+                text_params,
+            );
+        }
+    }
+}
+
+fn draw_tile_outline(coord: &Coord, color: Color) {
+    draw_rectangle_lines(coord.x as f32 * 16.0, coord.y as f32 * 16.0, 16.0, 16.0, 1.0, color);
+}
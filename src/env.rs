@@ -0,0 +1,163 @@
+use crate::{
+    grid::Coord,
+    level::{GameState, Level},
+    replay::{ReplayStep, REPLAY_DELTA_TIME, REPLAY_TICKS_PER_STEP},
+    tile_data::{tile_move::TileMove, tile_type::TileType},
+};
+
+/// What the agent can do for one step, a step being one tile move like in replays.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Wait,
+    Up,
+    Right,
+    Down,
+    Left,
+    SlurpUp,
+    SlurpRight,
+    SlurpDown,
+    SlurpLeft,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::Wait,
+        Action::Up,
+        Action::Right,
+        Action::Down,
+        Action::Left,
+        Action::SlurpUp,
+        Action::SlurpRight,
+        Action::SlurpDown,
+        Action::SlurpLeft,
+    ];
+
+    /// The action at `index` in `ALL`, for agents that pick actions by number.
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    pub const fn to_step(self) -> ReplayStep {
+        let (mov, slurping) = match self {
+            Action::Wait => (TileMove::None, false),
+            Action::Up => (TileMove::Up, false),
+            Action::Right => (TileMove::Right, false),
+            Action::Down => (TileMove::Down, false),
+            Action::Left => (TileMove::Left, false),
+            Action::SlurpUp => (TileMove::Up, true),
+            Action::SlurpRight => (TileMove::Right, true),
+            Action::SlurpDown => (TileMove::Down, true),
+            Action::SlurpLeft => (TileMove::Left, true),
+        };
+        ReplayStep { mov, slurping }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rewards {
+    pub infotron: f32,
+    pub exit: f32,
+    pub death: f32,
+    /// Added every step, a small negative value favours short solutions.
+    pub step: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Self {
+            infotron: 1.0,
+            exit: 10.0,
+            death: -10.0,
+            step: -0.01,
+        }
+    }
+}
+
+/// The grid as `TileType::index` values in row-major order, and where Murphy is.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Observation {
+    pub width: i16,
+    pub height: i16,
+    pub tiles: Vec<u8>,
+    /// `None` once Murphy has exploded.
+    pub murphy: Option<Coord>,
+}
+
+impl Observation {
+    pub fn new(level: &Level) -> Self {
+        let grid = &level.grid;
+        Self {
+            width: grid.width,
+            height: grid.height,
+            tiles: grid.array.iter().map(|tile| tile.typ.index()).collect(),
+            murphy: grid.find(TileType::Murphy),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StepResult {
+    pub observation: Observation,
+    pub reward: f32,
+    /// The level was finished, Murphy died or `max_steps` was reached.
+    pub done: bool,
+    pub game_state: GameState,
+}
+
+/// A gym-style environment: `reset` with a level, then `step` until `done`. Runs the same
+/// fixed ticks as replays and the solver, without a window.
+pub struct Env {
+    level: Level,
+    steps: usize,
+    pub max_steps: usize,
+    pub rewards: Rewards,
+}
+
+impl Env {
+    pub fn new(level: &Level, max_steps: usize) -> Self {
+        Self {
+            level: level.clone(),
+            steps: 0,
+            max_steps,
+            rewards: Rewards::default(),
+        }
+    }
+
+    pub fn reset(&mut self, level: &Level) -> Observation {
+        self.level = level.clone();
+        self.steps = 0;
+        Observation::new(&self.level)
+    }
+
+    pub fn step(&mut self, action: Action) -> StepResult {
+        let infotrons_before = self.level.data.infotrons_required;
+        action
+            .to_step()
+            .play(&mut self.level, REPLAY_DELTA_TIME, REPLAY_TICKS_PER_STEP);
+        self.steps += 1;
+
+        let game_state = self.level.data.game_state;
+        let collected = infotrons_before - self.level.data.infotrons_required;
+        let mut reward = self.rewards.step + collected as f32 * self.rewards.infotron;
+        match game_state {
+            GameState::Active => {}
+            GameState::Died => reward += self.rewards.death,
+            GameState::Finished => reward += self.rewards.exit,
+        }
+
+        StepResult {
+            observation: Observation::new(&self.level),
+            reward,
+            done: game_state != GameState::Active || self.steps >= self.max_steps,
+            game_state,
+        }
+    }
+
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
@@ -1,9 +1,16 @@
-use crate::tile_data::{
-    tile::Tile, tile_interaction::TileInteraction, tile_move::TileMove,
-    tile_state::TileState, tile_type::TileType,
+use std::hash::{Hash, Hasher};
+
+use crate::{
+    schedule::Schedule,
+    tile_data::{
+        tile::Tile, tile_interaction::TileInteraction, tile_move::TileMove,
+        tile_state::TileState, tile_type::TileType,
+    },
 };
 
-const BORDER_TILE: Tile = Tile {
+/// What every coordinate outside the grid holds: nothing that could move, be eaten or be
+/// destroyed, on all four sides alike.
+pub const BORDER_TILE: Tile = Tile {
     upd: 0.0,
     typ: TileType::None,
     state: TileState::Indestructible,
@@ -17,6 +24,7 @@ pub struct Grid {
     pub width: i16,
     pub height: i16,
     pub array: Vec<Tile>,
+    pub schedule: Schedule,
 }
 
 impl Grid {
@@ -26,46 +34,162 @@ impl Grid {
             .map(|i| TileType::to_tile(TileType::from_u8(i)))
             .collect();
 
+        Self::from_tiles(width, height, array)
+    }
+
+    pub fn from_tiles(width: i16, height: i16, array: Vec<Tile>) -> Self {
         Self {
             width,
             height,
+            schedule: Schedule::new(array.len()),
             array,
         }
     }
 
+    /// The coordinate of `array[index]`.
+    pub fn coord(&self, index: usize) -> Coord {
+        Coord::new(index as i16 % self.width, index as i16 / self.width)
+    }
+
+    /// Wakes every tile, for after `array` was written to directly.
+    pub fn wake_all(&mut self) {
+        self.schedule.wake_all();
+    }
+
+    pub fn contains(&self, coord: &Coord) -> bool {
+        coord.x >= 0 && coord.y >= 0 && coord.x < self.width && coord.y < self.height
+    }
+
+    /// The index of `coord` in `array`, `None` outside the grid.
+    pub fn index(&self, coord: &Coord) -> Option<usize> {
+        self.contains(coord)
+            .then(|| coord.y as usize * self.width as usize + coord.x as usize)
+    }
+
+    /// The first tile of `typ` in row-major order.
+    pub fn find(&self, typ: TileType) -> Option<Coord> {
+        self.find_all(typ).next()
+    }
+
+    /// Every tile of `typ` in row-major order.
+    pub fn find_all(&self, typ: TileType) -> impl Iterator<Item = Coord> + '_ {
+        self.iter()
+            .filter(move |(_, tile)| tile.typ == typ)
+            .map(|(coord, _)| coord)
+    }
+
+    /// The tile at `coord`, `BORDER_TILE` outside the grid.
     pub fn get(&self, coord: &Coord) -> &Tile {
-        match self
-            .array
-            .get((coord.y * self.width + coord.x) as usize)
-        {
-            Some(tile) => tile,
+        match self.index(coord) {
+            Some(i) => &self.array[i],
             None => &BORDER_TILE,
         }
     }
 
+    /// The tile next to `coord` in the direction of `offset`.
+    pub fn neighbour(&self, coord: &Coord, offset: &impl ToCoord) -> &Tile {
+        self.get(&coord.offset(offset))
+    }
+
     pub fn get_mut(&mut self, coord: &Coord) -> Option<&mut Tile> {
-        self.array
-            .get_mut((coord.y * self.width + coord.x) as usize)
+        let i = self.index(coord)?;
+        Some(&mut self.array[i])
     }
 
-    pub fn get_mut_unchecked(&mut self, coord: &Coord) -> &mut Tile {
-        unsafe {
-            self.array
-                .get_unchecked_mut((coord.y * self.width + coord.x) as usize)
+    /// Changes a tile in place, nothing happens outside the grid. Unlike `set` this does not
+    /// wake the neighbours, it is meant for a tile changing itself during its own update.
+    pub fn modify(&mut self, coord: &Coord, change: impl FnOnce(&mut Tile)) {
+        if let Some(tile) = self.get_mut(coord) {
+            change(tile);
         }
     }
 
+    /// Replaces a tile and wakes it and its neighbours for the next update.
     pub fn set(&mut self, coord: &Coord, tile: Tile) {
-        if let Some(current_tile) = self
-            .array
-            .get_mut((coord.y * self.width + coord.x) as usize)
-        {
-            *current_tile = tile;
+        if let Some(index) = self.index(coord) {
+            self.array[index] = tile;
+            self.schedule.record_change(index);
         }
     }
+
+    /// Every tile with its coordinate, in row-major order.
+    pub fn iter(&self) -> impl Iterator<Item = (Coord, &Tile)> {
+        self.array
+            .iter()
+            .enumerate()
+            .map(|(i, tile)| (self.coord(i), tile))
+    }
+
+    /// The tiles sharing an edge with `coord` that lie inside the grid, clockwise from the
+    /// one above. Like `region` the iterator does not borrow the grid.
+    pub fn neighbours4(&self, coord: &Coord) -> impl Iterator<Item = Coord> {
+        let (coord, width, height) = (*coord, self.width, self.height);
+        TileMove::DIRECTIONS
+            .into_iter()
+            .map(move |mov| coord.offset(&mov))
+            .filter(move |n| n.x >= 0 && n.y >= 0 && n.x < width && n.y < height)
+    }
+
+    /// The up to eight tiles around `coord` inside the grid, in row-major order.
+    pub fn neighbours8(&self, coord: &Coord) -> impl Iterator<Item = Coord> {
+        let coord = *coord;
+        self.region(coord.offset(&(-1, -1)), coord.offset(&(1, 1)))
+            .filter(move |n| *n != coord)
+    }
+
+    /// Every coordinate of the grid in row-major order. The iterator does not borrow the
+    /// grid, so tiles can be changed while going through it.
+    pub fn coords(&self) -> Region {
+        self.region(Coord::new(0, 0), Coord::new(self.width - 1, self.height - 1))
+    }
+
+    /// The coordinates from `min` to `max`, both included, that lie inside the grid, in
+    /// row-major order. Like `coords` it does not borrow the grid.
+    pub fn region(&self, min: Coord, max: Coord) -> Region {
+        let min = Coord::new(min.x.max(0), min.y.max(0));
+        let max = Coord::new(max.x.min(self.width - 1), max.y.min(self.height - 1));
+        Region {
+            min_x: min.x,
+            max,
+            next: (min.x <= max.x && min.y <= max.y).then_some(min),
+        }
+    }
+}
+
+/// Only what the level looks like, not which tiles happen to be awake.
+impl Hash for Grid {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.width.hash(state);
+        self.height.hash(state);
+        self.array.hash(state);
+    }
+}
+
+/// Iterator over a rectangle of coordinates, see `Grid::region`.
+#[derive(Clone, Debug)]
+pub struct Region {
+    min_x: i16,
+    max: Coord,
+    next: Option<Coord>,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Iterator for Region {
+    type Item = Coord;
+
+    fn next(&mut self) -> Option<Coord> {
+        let coord = self.next?;
+        self.next = if coord.x < self.max.x {
+            Some(Coord::new(coord.x + 1, coord.y))
+        } else if coord.y < self.max.y {
+            Some(Coord::new(self.min_x, coord.y + 1))
+        } else {
+            None
+        };
+        Some(coord)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Coord {
     pub x: i16,
     pub y: i16,
@@ -144,3 +268,68 @@ impl ToCoord for (i16, i16) {
         Coord { x: self.0, y: self.1 }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_loader::parse_level_text;
+
+    fn grid(text: &str) -> Grid {
+        parse_level_text(text).unwrap().grid
+    }
+
+    #[test]
+    fn every_side_outside_the_grid_is_border() {
+        let grid = grid("O..\n..O");
+        for coord in [(-1, 1), (3, 0), (0, -1), (2, 2), (-1, -1)] {
+            let coord = Coord::new(coord.0, coord.1);
+            assert!(!grid.contains(&coord));
+            assert_eq!(grid.get(&coord), &BORDER_TILE, "{coord:?}");
+        }
+        // left of the second row, not the end of the first
+        assert_eq!(grid.neighbour(&Coord::new(0, 1), &TileMove::Left), &BORDER_TILE);
+        assert_eq!(grid.neighbour(&Coord::new(1, 1), &TileMove::Right).typ, TileType::Zonk);
+    }
+
+    #[test]
+    fn changes_outside_the_grid_are_ignored() {
+        let mut grid = grid("O..\n..O");
+        let before = grid.array.clone();
+        grid.set(&Coord::new(3, 0), TileType::Murphy.to_tile());
+        grid.modify(&Coord::new(-1, 1), |tile| tile.typ = TileType::Murphy);
+        assert!(grid.get_mut(&Coord::new(0, 2)).is_none());
+        assert_eq!(grid.array, before);
+    }
+
+    #[test]
+    fn regions_are_clipped_to_the_grid() {
+        let grid = grid("...\n...");
+        let coords = |region: Region| region.map(|c| (c.x, c.y)).collect::<Vec<_>>();
+        assert_eq!(coords(grid.region(Coord::new(1, -5), Coord::new(9, 0))), [(1, 0), (2, 0)]);
+        assert_eq!(coords(grid.region(Coord::new(4, 0), Coord::new(9, 1))), []);
+        assert_eq!(coords(grid.coords()).len(), 6);
+        assert_eq!(grid.coord(4), Coord::new(1, 1));
+        assert_eq!(grid.index(&Coord::new(1, 1)), Some(4));
+    }
+
+    #[test]
+    fn neighbourhoods_leave_out_what_is_outside() {
+        let grid = grid("...\n...\n...");
+        let coords = |coords: Vec<Coord>| coords.iter().map(|c| (c.x, c.y)).collect::<Vec<_>>();
+        let center = Coord::new(1, 1);
+        let corner = Coord::new(0, 0);
+        assert_eq!(coords(grid.neighbours4(&center).collect()), [(1, 0), (2, 1), (1, 2), (0, 1)]);
+        assert_eq!(coords(grid.neighbours4(&corner).collect()), [(1, 0), (0, 1)]);
+        assert_eq!(grid.neighbours8(&center).count(), 8);
+        assert_eq!(coords(grid.neighbours8(&corner).collect()), [(1, 0), (0, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn find_all_goes_in_row_major_order() {
+        let grid = grid(".*.\n*.*");
+        let infotrons: Vec<Coord> = grid.find_all(TileType::Infotron).collect();
+        assert_eq!(infotrons, [Coord::new(1, 0), Coord::new(0, 1), Coord::new(2, 1)]);
+        assert_eq!(grid.find(TileType::Infotron), Some(Coord::new(1, 0)));
+        assert_eq!(grid.find(TileType::Murphy), None);
+    }
+}
@@ -0,0 +1,318 @@
+use std::fmt;
+
+/// A small JSON value, enough for the line-based protocols of the bot and netplay modes.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys keep their order, so messages are written the way they were built.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Self {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Whole, non-negative numbers only.
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|n| n.fract() == 0.0 && *n >= 0.0 && *n <= usize::MAX as f64)
+            .map(|n| n as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: text.char_indices().peekable(),
+            text,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some((i, _)) => Err(format!("unexpected data at {i}")),
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+macro_rules! json_from_number {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Json {
+            fn from(value: $t) -> Self {
+                Json::Number(value as f64)
+            }
+        })*
+    };
+}
+json_from_number!(i16, i32, u32, usize, f32, f64);
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) if n.is_finite() => write!(f, "{n}"),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    text: &'a str,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((i, c)) => Err(format!("expected {expected:?} at {i}, found {c:?}")),
+            None => Err(format!("expected {expected:?}, found the end")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        let Some(&(start, c)) = self.chars.peek() else {
+            return Err("unexpected end".to_string());
+        };
+        match c {
+            '{' => self.object(),
+            '[' => self.array(),
+            '"' => self.string().map(Json::String),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            'n' => self.keyword("null", Json::Null),
+            '-' | '0'..='9' => {
+                let mut end = start;
+                while let Some((i, c)) = self
+                    .chars
+                    .next_if(|(_, c)| matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+                {
+                    end = i + c.len_utf8();
+                }
+                let number = &self.text[start..end];
+                number
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| format!("bad number: {number}"))
+            }
+            c => Err(format!("unexpected {c:?} at {start}")),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(s),
+                Some((i, '\\')) => match self.chars.next() {
+                    Some((_, '"')) => s.push('"'),
+                    Some((_, '\\')) => s.push('\\'),
+                    Some((_, '/')) => s.push('/'),
+                    Some((_, 'b')) => s.push('\u{8}'),
+                    Some((_, 'f')) => s.push('\u{c}'),
+                    Some((_, 'n')) => s.push('\n'),
+                    Some((_, 'r')) => s.push('\r'),
+                    Some((_, 't')) => s.push('\t'),
+                    Some((_, 'u')) => {
+                        let hex: String = (0..4).filter_map(|_| self.chars.next()).map(|(_, c)| c).collect();
+                        let c = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("bad escape at {i}"))?;
+                        s.push(c);
+                    }
+                    _ => return Err(format!("bad escape at {i}")),
+                },
+                Some((_, c)) => s.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, c)| *c == ']').is_some() {
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, ']')) => return Ok(Json::Array(items)),
+                _ => return Err("expected , or ] in array".to_string()),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, c)| *c == '}').is_some() {
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, '}')) => return Ok(Json::Object(fields)),
+                _ => return Err("expected , or } in object".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_values_are_parsed() {
+        let text = r#" {"cmd": "step", "ticks": 8, "list": [true, null, -1.5e1], "o": {}} "#;
+        let json = Json::parse(text).unwrap();
+        assert_eq!(json.get("cmd").and_then(Json::as_str), Some("step"));
+        assert_eq!(json.get("ticks").and_then(Json::as_usize), Some(8));
+        assert_eq!(
+            json.get("list"),
+            Some(&Json::Array(vec![Json::Bool(true), Json::Null, Json::Number(-15.0)]))
+        );
+        assert_eq!(json.get("o"), Some(&Json::Object(Vec::new())));
+    }
+
+    #[test]
+    fn strings_survive_writing_and_parsing() {
+        let value = Json::object([("text", "a \"quote\"\\\n\tand \u{1}".into()), ("n", 3.into())]);
+        let written = value.to_string();
+        assert_eq!(written, r#"{"text":"a \"quote\"\\\n\tand \u0001","n":3}"#);
+        assert_eq!(Json::parse(&written), Ok(value));
+        assert_eq!(Json::parse(r#""é\/""#), Ok(Json::from("é/")));
+    }
+
+    #[test]
+    fn broken_json_is_an_error() {
+        for text in ["", "{", "[1,]", r#"{"a" 1}"#, r#""open"#, "tru", "1 2", r#""\x""#] {
+            assert!(Json::parse(text).is_err(), "{text:?} parsed");
+        }
+    }
+
+    #[test]
+    fn only_whole_non_negative_numbers_are_usize() {
+        assert_eq!(Json::Number(2.5).as_usize(), None);
+        assert_eq!(Json::Number(-1.0).as_usize(), None);
+        assert_eq!(Json::from("3").as_usize(), None);
+    }
+}
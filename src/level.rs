@@ -1,9 +1,8 @@
 use std::time::{Duration, Instant};
 
 use macroquad::{
-    prelude::{is_key_pressed, is_key_released, KeyCode, Vec2},
+    prelude::{is_key_down, is_key_pressed, is_key_released, KeyCode, Vec2},
     text::{draw_text_ex, TextParams, measure_text},
-    texture::Texture2D,
     time::get_frame_time,
     window::{screen_height, screen_width},
 };
@@ -11,17 +10,10 @@ use macroquad::{
 use crate::{
     grid::{Coord, FCoord, Grid},
     tile_data::{
-        systems::{
-            ai_system::ai_system,
-            explode_system::explode_system,
-            gravity_system::gravity_system,
-            murphy_system::{murphy_system, set_cam_pos},
-            orange_disk_system::orange_disk_system,
-            transitory_system::transitory_system,
-        },
+        behavior::registry,
+        sprite::SpriteTarget,
         tile_move::TileMove,
         tile_type::{draw_time, TileType},
-        tile_update::Updateable,
     },
 };
 
@@ -38,6 +30,7 @@ impl Level {
         Self {
             data: LevelData {
                 move_priority: Vec::new(),
+                slurping: false,
                 name: "N/A".to_string(),
                 speed,
                 delta_time: 0.0,
@@ -48,97 +41,222 @@ impl Level {
                 red_utility_disks: 7,
                 gravity_enabled: false,
                 zonks_frozen: false,
+                special_ports: Vec::new(),
+                players: Vec::new(),
+                exit_rule: ExitRule::Any,
             },
             grid,
         }
     }
 
-    pub fn update(&mut self) {
-        self.data.delta_time = get_frame_time() * self.data.speed;
+    /// A copy of this level with Murphy moved and its counters replaced, the original is
+    /// left untouched so it can still be used to restart.
+    pub fn with_start(&self, start: &StartOverride) -> Self {
+        let mut level = self.clone();
 
-        if is_key_released(KeyCode::Up) {
-            self.data
-                .move_priority
-                .retain(|mc| !matches!(mc, TileMove::Up));
+        if let Some(coord) = start.murphy {
+            level.move_murphy(coord);
         }
-        if is_key_released(KeyCode::Down) {
-            self.data
-                .move_priority
-                .retain(|mc| !matches!(mc, TileMove::Down));
+        if let Some(infotrons_required) = start.infotrons_required {
+            level.data.infotrons_required = infotrons_required;
         }
-        if is_key_released(KeyCode::Left) {
-            self.data
-                .move_priority
-                .retain(|mc| !matches!(mc, TileMove::Left));
+        if let Some(red_utility_disks) = start.red_utility_disks {
+            level.data.red_utility_disks = red_utility_disks;
         }
-        if is_key_released(KeyCode::Right) {
-            self.data
-                .move_priority
-                .retain(|mc| !matches!(mc, TileMove::Right));
+        if let Some(gravity_enabled) = start.gravity_enabled {
+            level.data.gravity_enabled = gravity_enabled;
         }
 
-        if is_key_pressed(KeyCode::Up) {
-            self.data.move_priority.insert(0, TileMove::Up);
+        level
+    }
+
+    /// Replaces every Murphy with empty space and puts a single one at `coord`, nothing
+    /// happens outside the grid.
+    pub fn move_murphy(&mut self, coord: Coord) {
+        if !self.grid.contains(&coord) {
+            return;
         }
-        if is_key_pressed(KeyCode::Down) {
-            self.data.move_priority.insert(0, TileMove::Down);
+        for tile in self.grid.array.iter_mut() {
+            if tile.typ == TileType::Murphy {
+                *tile = TileType::Empty.to_tile();
+            }
         }
-        if is_key_pressed(KeyCode::Left) {
-            self.data.move_priority.insert(0, TileMove::Left);
+        self.grid.set(&coord, TileType::Murphy.to_tile());
+        self.grid.wake_all();
+    }
+
+    /// Gives every Murphy in the level its own input, in row-major order, and returns how
+    /// many players there are.
+    pub fn add_players(&mut self, exit_rule: ExitRule) -> usize {
+        self.data.players = (0..self.grid.height)
+            .flat_map(|y| (0..self.grid.width).map(move |x| Coord::new(x, y)))
+            .filter(|coord| self.grid.get(coord).typ == TileType::Murphy)
+            .map(Player::new)
+            .collect();
+        self.data.exit_rule = exit_rule;
+        self.data.players.len()
+    }
+
+    pub fn update(&mut self) {
+        self.read_keys();
+        self.tick(get_frame_time() * self.data.speed);
+    }
+
+    /// Takes the held keys as the input of the next tick.
+    pub fn read_keys(&mut self) {
+        if self.data.players.is_empty() {
+            ARROW_KEYS.read(&mut self.data.move_priority, &mut self.data.slurping);
         }
-        if is_key_pressed(KeyCode::Right) {
-            self.data.move_priority.insert(0, TileMove::Right);
+        for (player, keys) in self.data.players.iter_mut().zip(PLAYER_KEYS) {
+            keys.read(&mut player.move_priority, &mut player.slurping);
         }
+    }
+
+    /// Advances the simulation by `delta_time` without reading any input or window state,
+    /// the same `delta_time` and inputs always produce the same result.
+    ///
+    /// Tiles are updated in the order of the original game, row by row from the top and
+    /// left to right within a row, and each at most once: a tile that moves or is pushed
+    /// down or to the right, or any other tile written further along the scan, has been
+    /// updated for this tick and is skipped when the scan reaches its new cell.
+    pub fn tick(&mut self, delta_time: f32) {
+        self.data.delta_time = delta_time;
 
         let now = Instant::now();
-        self.grid.array.iter_mut().for_each(|tile| {
-            tile.update_time(self.data.delta_time);
-        });
-        for y in 0..self.grid.height {
-            for x in 0..self.grid.width {
-                let coord = Coord::new(x, y);
-                match self.grid.get(&coord).typ {
-                    TileType::Bug => {}
-                    TileType::Electron => {}
-                    TileType::Infotron => gravity_system(&coord, &mut self.grid, &mut self.data),
-                    TileType::Murphy => {
-                        let coord = murphy_system(&coord, &mut self.grid, &mut self.data);
-                        let murphy = self.grid.get(&coord);
-                        set_cam_pos(murphy, &coord, &mut self.data);
-                    }
-                    TileType::OrangeUtilityDisk => {
-                        orange_disk_system(&coord, &mut self.grid, &mut self.data)
-                    }
-                    TileType::RedUtilityDisk => {}
-                    TileType::Terminal => {}
-                    TileType::Transitory => {
-                        transitory_system(&coord, &mut self.grid, &mut self.data)
-                    }
-                    TileType::YellowUtilityDisk => {}
-                    TileType::SnikSnak => ai_system(&coord, &mut self.grid, &mut self.data),
-                    TileType::Zonk => gravity_system(&coord, &mut self.grid, &mut self.data),
-                    TileType::Explosion | TileType::Explosion2 => {
-                        explode_system(&coord, &mut self.grid, &mut self.data);
-                    }
-                    _ => {}
-                }
+        // nothing counts down while waking up between ticks, all awake tiles do below
+        let len = self.grid.array.len();
+        let mut changed = Vec::new();
+        if self.grid.schedule.take_stale() {
+            self.grid.schedule.clear();
+            for i in 0..len {
+                self.wake(i, len);
             }
+        } else {
+            self.grid.schedule.take_changes(&mut changed);
+            self.wake_changed(&mut changed, len);
         }
 
-        if self.data.game_state == GameState::Died {
-            //self.data.speed = 0.0;
+        let mut next = 0;
+        while let Some(i) = self.grid.schedule.next_awake(next) {
+            self.grid.array[i].update_time(self.data.delta_time);
+            next = i + 1;
         }
 
         // You might ask: why tf 2 loops???
-        // because update_time for every tile needs to happen before a tile moves to a different position, iterating is very negliable at 1440 tiles const.
+        // because update_time for every tile needs to happen before a tile moves to a different position.
+        self.grid.schedule.clear_updated();
+        let mut next = 0;
+        while let Some(i) = self.grid.schedule.next_awake(next) {
+            next = i + 1;
+            if self.grid.schedule.is_updated(i) {
+                continue;
+            }
+            let coord = self.grid.coord(i);
+            let before = self.grid.array[i].clone();
+            let behavior = registry().get(before.typ);
+            let moved_to = match before.typ {
+                TileType::Murphy => self.update_murphy(coord),
+                _ => behavior.update(&coord, &mut self.grid, &mut self.data),
+            };
+
+            // a tile changing in place, like Murphy starting to push, can matter to its
+            // neighbours too, only its timer cannot
+            let after = &self.grid.array[i];
+            let same = after.same_state(&before);
+            let settled = same && after.upd == 0.0;
+            self.grid.schedule.take_changes(&mut changed);
+            for &written in &changed {
+                if written as usize >= next {
+                    self.grid.schedule.mark_updated(written as usize);
+                }
+            }
+            if !same {
+                changed.push(i as u32);
+            }
+            if settled && moved_to == coord && changed.is_empty() && !behavior.always_active() {
+                self.grid.schedule.sleep(i);
+            }
+            self.wake_changed(&mut changed, next);
+        }
+
         self.data.update_duration = now.elapsed();
     }
 
-    pub fn draw(&self, texture: Texture2D) {
+    /// Wakes the written tiles and their neighbours, then empties `changed`.
+    fn wake_changed(&mut self, changed: &mut Vec<u32>, next: usize) {
+        // a tile written this tick does not count down until the next one
+        for &i in changed.iter() {
+            let typ = self.grid.array[i as usize].typ;
+            if registry().get(typ).animated() {
+                self.grid.schedule.wake(i as usize);
+            }
+        }
+        for &i in changed.iter() {
+            self.wake_around(i as usize, next);
+        }
+        changed.clear();
+    }
+
+    fn wake_around(&mut self, index: usize, next: usize) {
+        let coord = self.grid.coord(index);
+        for neighbour in self.grid.neighbours8(&coord) {
+            let width = self.grid.width as usize;
+            self.wake(neighbour.y as usize * width + neighbour.x as usize, next);
+        }
+    }
+
+    /// Wakes a tile if it acts at all. One that is still to be updated this tick also
+    /// counts down, every tile that was awake at the start of the tick already did.
+    fn wake(&mut self, index: usize, next: usize) {
+        let tile = &mut self.grid.array[index];
+        if registry().get(tile.typ).animated() && self.grid.schedule.wake(index) && index >= next {
+            tile.update_time(self.data.delta_time);
+        }
+    }
+
+    fn update_murphy(&mut self, coord: Coord) -> Coord {
+        // a player's input is swapped in while its Murphy moves
+        let player = self
+            .data
+            .players
+            .iter()
+            .position(|player| player.coord == coord && !player.exited);
+        if let Some(i) = player {
+            self.data.players[i].swap_input(&mut self.data.move_priority, &mut self.data.slurping);
+        }
+
+        let finished_before = self.data.game_state == GameState::Finished;
+        let coord = registry()
+            .get(TileType::Murphy)
+            .update(&coord, &mut self.grid, &mut self.data);
+
+        let Some(i) = player else {
+            return coord;
+        };
+        let player = &mut self.data.players[i];
+        player.swap_input(&mut self.data.move_priority, &mut self.data.slurping);
+        player.coord = coord;
+        player.camera_target = self.data.camera_target;
+
+        // with ExitRule::All a Murphy reaching the exit leaves and the others play on
+        if self.data.exit_rule == ExitRule::All
+            && !finished_before
+            && self.data.game_state == GameState::Finished
+        {
+            player.exited = true;
+            self.grid.set(&coord, TileType::Empty.to_tile());
+            if !self.data.players.iter().all(|player| player.exited) {
+                self.data.game_state = GameState::Active;
+            }
+        }
+        coord
+    }
+
+    pub fn draw(&self, mut target: impl SpriteTarget) {
         for y in 0..self.grid.height {
             for x in 0..self.grid.width {
                 let coord = Coord::new(x, y);
-                self.grid.get(&coord).draw(&coord.as_fcoord(), texture);
+                self.grid.get(&coord).draw(&coord.as_fcoord(), &mut target);
             }
         }
     }
@@ -199,6 +317,7 @@ impl Level {
 #[derive(Clone)]
 pub struct LevelData {
     pub move_priority: Vec<TileMove>,
+    pub slurping: bool,
     pub name: String,
     pub speed: f32,
     pub delta_time: f32,
@@ -209,9 +328,119 @@ pub struct LevelData {
     pub red_utility_disks: u32,
     pub gravity_enabled: bool,
     pub zonks_frozen: bool,
+    pub special_ports: Vec<SpecialPort>,
+    /// Empty for single player, where every Murphy follows `move_priority` and `slurping`.
+    pub players: Vec<Player>,
+    pub exit_rule: ExitRule,
+}
+
+/// When a level with several players is finished.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum ExitRule {
+    /// As soon as one Murphy reaches the exit.
+    #[default]
+    Any,
+    /// Once every Murphy has reached the exit, each leaving the level when it does.
+    All,
+}
+
+/// The keys one player moves with.
+#[derive(Clone, Copy, Debug)]
+pub struct KeySet {
+    pub up: KeyCode,
+    pub right: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub slurp: KeyCode,
+}
+
+pub const ARROW_KEYS: KeySet = KeySet {
+    up: KeyCode::Up,
+    right: KeyCode::Right,
+    down: KeyCode::Down,
+    left: KeyCode::Left,
+    slurp: KeyCode::Space,
+};
+
+pub const WASD_KEYS: KeySet = KeySet {
+    up: KeyCode::W,
+    right: KeyCode::D,
+    down: KeyCode::S,
+    left: KeyCode::A,
+    slurp: KeyCode::Q,
+};
+
+/// Keys of the first and second player when a level is played together.
+pub const PLAYER_KEYS: [KeySet; 2] = [ARROW_KEYS, WASD_KEYS];
+
+impl KeySet {
+    /// The last pressed direction that is still held comes first.
+    pub fn read(&self, move_priority: &mut Vec<TileMove>, slurping: &mut bool) {
+        let keys = [
+            (self.up, TileMove::Up),
+            (self.down, TileMove::Down),
+            (self.left, TileMove::Left),
+            (self.right, TileMove::Right),
+        ];
+        for (key, mov) in keys {
+            if is_key_released(key) {
+                move_priority.retain(|mc| *mc != mov);
+            }
+        }
+        for (key, mov) in keys {
+            if is_key_pressed(key) {
+                move_priority.insert(0, mov);
+            }
+        }
+        *slurping = is_key_down(self.slurp);
+    }
+}
+
+/// One Murphy with its own input, found by the tile it stands on.
+#[derive(Clone, Debug)]
+pub struct Player {
+    pub coord: Coord,
+    pub move_priority: Vec<TileMove>,
+    pub slurping: bool,
+    /// Set once this Murphy has left through the exit, see `ExitRule::All`.
+    pub exited: bool,
+    pub camera_target: Option<Vec2>,
+}
+
+impl Player {
+    pub fn new(coord: Coord) -> Self {
+        Self {
+            coord,
+            move_priority: Vec::new(),
+            slurping: false,
+            exited: false,
+            camera_target: None,
+        }
+    }
+
+    fn swap_input(&mut self, move_priority: &mut Vec<TileMove>, slurping: &mut bool) {
+        std::mem::swap(&mut self.move_priority, move_priority);
+        std::mem::swap(&mut self.slurping, slurping);
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct StartOverride {
+    pub murphy: Option<Coord>,
+    pub infotrons_required: Option<i32>,
+    pub red_utility_disks: Option<u32>,
+    pub gravity_enabled: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SpecialPort {
+    pub coord: Coord,
+    pub gravity_enabled: bool,
+    pub zonks_frozen: bool,
+    pub enemies_frozen: bool,
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum GameState {
     Active,
     Died,
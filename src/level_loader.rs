@@ -1,53 +1,483 @@
-use std::{fs::{File, self}, io, time::Duration};
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    time::Duration,
+};
 
-use crate::{level::{Level, LevelData}, grid::Grid};
+use crate::{
+    grid::{Coord, Grid},
+    level::{ExitRule, Level, LevelData, SpecialPort},
+    tile_data::tile_type::TileType,
+};
+
+pub const LEVEL_WIDTH: i16 = 60;
+pub const LEVEL_HEIGHT: i16 = 24;
+pub const LEVEL_TILES: usize = 1440;
+pub const LEVEL_SIZE: usize = 1536;
+/// Header stored after the tiles of every level, in DAT, SP and MPX files alike. Offsets
+/// into it: 4 unused bytes, gravity (1 is on), the speed fix version, the name (23 bytes),
+/// frozen zonks (2 is on), infotrons required, the special port count and 10 special ports
+/// of 6 bytes, the rest is unused.
+pub const LEVEL_INFO_SIZE: usize = LEVEL_SIZE - LEVEL_TILES;
+
+const INFO_GRAVITY: usize = 4;
+const INFO_NAME: std::ops::Range<usize> = 6..29;
+const INFO_ZONKS_FROZEN: usize = 29;
+const INFO_INFOTRONS: usize = 30;
+const INFO_SPECIAL_PORT_COUNT: usize = 31;
+const INFO_SPECIAL_PORTS: std::ops::Range<usize> = 32..92;
+
+const MPX_MAGIC: &[u8; 4] = b"MPX ";
+const MPX_ENTRY_SIZE: usize = 12;
 
 pub fn load_level_dat(path: &str) -> io::Result<Vec<Level>> {
     let data = fs::read(path)?;
 
-    let mut levels = Vec::new();
-    
-    for i in 0..111 {
-        let level_offset = i * 1536;
-
-        // tiles
-        let tiles_array = &data[level_offset..(1440 + level_offset)];
-
-        // gravity enabled
-        let gravity = data[1444 + level_offset];
-
-        // Name
-        let name = &data[(1444 + level_offset)..(1467 + level_offset)];
-
-        // zonks frozen
-        let zonk_buf = data[1469 + level_offset];
-
-        // infotron count
-        let infotrons = data[1470 + level_offset];
-
-        let tiles = Grid::new(60, 24, tiles_array.to_vec());
-        let gravity_enabled = gravity == 0;
-        let name = String::from_utf8(name.to_vec()).unwrap();
-        let zonks_frozen = zonk_buf == 0;
-
-        let level = Level {
-            data: LevelData {
-                move_priority: Vec::new(),
-                name,
-                speed: 8.0,
-                delta_time: 0.0,
-                update_duration: Duration::ZERO,
-                camera_target: None,
-                game_state: crate::level::GameState::Active,
-                infotrons_required: infotrons as i32,
-                red_utility_disks: 0,
-                gravity_enabled,
-                zonks_frozen,
-            },
-            grid: tiles,
-        };
-        levels.push(level);
+    Ok(data.chunks_exact(LEVEL_SIZE).map(parse_level).collect())
+}
+
+pub fn save_level_dat(path: &str, levels: &[Level]) -> io::Result<()> {
+    let data = levels
+        .iter()
+        .map(level_to_bytes)
+        .collect::<io::Result<Vec<_>>>()?;
+    fs::write(path, data.concat())
+}
+
+/// A single level, optionally followed by a recorded demo which is ignored.
+pub fn load_level_sp(path: &str) -> io::Result<Level> {
+    let data = fs::read(path)?;
+    if data.len() < LEVEL_SIZE {
+        return Err(invalid_data(format!("{path}: too short for a level")));
+    }
+
+    Ok(parse_level(&data[..LEVEL_SIZE]))
+}
+
+/// Megaplex level sets: a `MPX ` header, a version and level count, then a width, height,
+/// offset and size for each level, all little endian. Levels can be larger than 60x24.
+pub fn load_level_mpx(path: &str) -> io::Result<Vec<Level>> {
+    let data = fs::read(path)?;
+    if data.len() < 8 || &data[..4] != MPX_MAGIC {
+        return Err(invalid_data(format!("{path}: not an MPX file")));
+    }
+
+    let count = u16::from_le_bytes([data[6], data[7]]) as usize;
+    let mut levels = Vec::with_capacity(count);
+    for i in 0..count {
+        let entry = data
+            .get(8 + i * MPX_ENTRY_SIZE..8 + (i + 1) * MPX_ENTRY_SIZE)
+            .ok_or_else(|| invalid_data(format!("{path}: truncated level table")))?;
+        let width = u16::from_le_bytes([entry[0], entry[1]]) as usize;
+        let height = u16::from_le_bytes([entry[2], entry[3]]) as usize;
+        let offset = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]) as usize;
+
+        let tiles = width * height;
+        if tiles == 0 || tiles > i16::MAX as usize {
+            return Err(invalid_data(format!("{path}: level {} has a bad size", i + 1)));
+        }
+        let level = data
+            .get(offset..offset + tiles + LEVEL_INFO_SIZE)
+            .ok_or_else(|| invalid_data(format!("{path}: level {} out of range", i + 1)))?;
+
+        let grid = Grid::new(width as i16, height as i16, level[..tiles].to_vec());
+        levels.push(parse_level_info(grid, &level[tiles..]));
     }
 
     Ok(levels)
-}
\ No newline at end of file
+}
+
+pub fn save_level_mpx(path: &str, levels: &[Level]) -> io::Result<()> {
+    let mut data = MPX_MAGIC.to_vec();
+    data.extend(1u16.to_le_bytes());
+    data.extend((levels.len() as u16).to_le_bytes());
+
+    let mut offset = data.len() + levels.len() * MPX_ENTRY_SIZE;
+    let mut bodies = Vec::new();
+    for level in levels {
+        let body = level_to_bytes(level)?;
+        data.extend((level.grid.width as u16).to_le_bytes());
+        data.extend((level.grid.height as u16).to_le_bytes());
+        data.extend((offset as u32).to_le_bytes());
+        data.extend((body.len() as u32).to_le_bytes());
+        offset += body.len();
+        bodies.extend(body);
+    }
+    data.extend(bodies);
+
+    fs::write(path, data)
+}
+
+/// Text levels: `key: value` header lines followed by one line per row of tiles,
+/// using the characters from `TileType::to_char`.
+/// ```text
+/// name: WARM UP
+/// infotrons: 4
+/// gravity: off
+/// zonks_frozen: off
+/// special_port: 12,3 gravity
+/// ######
+/// #M* E#
+/// ######
+/// ```
+pub fn load_level_text(path: &str) -> io::Result<Level> {
+    parse_level_text(&fs::read_to_string(path)?)
+        .map_err(|err| invalid_data(format!("{path}: {err}")))
+}
+
+pub fn save_level_text(path: &str, level: &Level) -> io::Result<()> {
+    fs::write(path, level_to_text(level))
+}
+
+pub fn parse_level_text(text: &str) -> Result<Level, String> {
+    let mut name = String::new();
+    let mut infotrons_required = 0;
+    let mut gravity_enabled = false;
+    let mut zonks_frozen = false;
+    let mut special_ports = Vec::new();
+    let mut rows: Vec<Vec<TileType>> = Vec::new();
+
+    for line in text.lines() {
+        let header = line.split_once(": ").filter(|(key, _)| {
+            rows.is_empty() && key.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+        });
+        if let Some((key, value)) = header {
+            match key {
+                "name" => name = value.to_string(),
+                "infotrons" => {
+                    infotrons_required =
+                        value.parse().map_err(|_| format!("bad infotrons: {value}"))?
+                }
+                "gravity" => gravity_enabled = parse_switch(value)?,
+                "zonks_frozen" => zonks_frozen = parse_switch(value)?,
+                "special_port" => special_ports.push(parse_special_port(value)?),
+                _ => return Err(format!("unknown key: {key}")),
+            }
+            continue;
+        }
+
+        let row = line
+            .chars()
+            .map(|c| TileType::from_char(c).ok_or_else(|| format!("unknown tile: {c:?}")))
+            .collect::<Result<_, _>>()?;
+        rows.push(row);
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let height = rows.len();
+    if width == 0 || width * height > i16::MAX as usize {
+        return Err("level must have between 1 and 32767 tiles".to_string());
+    }
+
+    // built from the types directly, the blue two-way ports have no byte to go through
+    let tiles = rows
+        .iter()
+        .flat_map(|row| (0..width).map(|x| row.get(x).copied().unwrap_or(TileType::Empty).to_tile()))
+        .collect();
+    let grid = Grid::from_tiles(width as i16, height as i16, tiles);
+
+    // the header comes first, so ports can only be checked once the rows are known
+    if let Some(port) = special_ports.iter().find(|port| !grid.contains(&port.coord)) {
+        return Err(format!(
+            "special port {},{} is outside the level",
+            port.coord.x, port.coord.y
+        ));
+    }
+
+    Ok(new_level(
+        grid,
+        name,
+        infotrons_required,
+        gravity_enabled,
+        zonks_frozen,
+        special_ports,
+    ))
+}
+
+pub fn level_to_text(level: &Level) -> String {
+    let on_off = |value: bool| if value { "on" } else { "off" };
+    let data = &level.data;
+
+    let mut text = format!(
+        "name: {}\ninfotrons: {}\ngravity: {}\nzonks_frozen: {}\n",
+        data.name.trim_end(),
+        data.infotrons_required,
+        on_off(data.gravity_enabled),
+        on_off(data.zonks_frozen),
+    );
+    for port in &data.special_ports {
+        text += &format!("special_port: {},{}", port.coord.x, port.coord.y);
+        for (flag, name) in [
+            (port.gravity_enabled, "gravity"),
+            (port.zonks_frozen, "zonks_frozen"),
+            (port.enemies_frozen, "enemies_frozen"),
+        ] {
+            if flag {
+                text += " ";
+                text += name;
+            }
+        }
+        text += "\n";
+    }
+    for row in level.grid.array.chunks(level.grid.width as usize) {
+        text.extend(row.iter().map(|tile| tile.typ.to_char()));
+        text += "\n";
+    }
+
+    text
+}
+
+fn parse_switch(value: &str) -> Result<bool, String> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("expected on or off: {value}")),
+    }
+}
+
+fn parse_special_port(value: &str) -> Result<SpecialPort, String> {
+    let mut words = value.split_whitespace();
+    let (x, y) = words
+        .next()
+        .and_then(|coord| coord.split_once(','))
+        .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)))
+        .ok_or_else(|| format!("bad special port: {value}"))?;
+
+    let mut port = SpecialPort {
+        coord: Coord::new(x, y),
+        gravity_enabled: false,
+        zonks_frozen: false,
+        enemies_frozen: false,
+    };
+    for word in words {
+        match word {
+            "gravity" => port.gravity_enabled = true,
+            "zonks_frozen" => port.zonks_frozen = true,
+            "enemies_frozen" => port.enemies_frozen = true,
+            _ => return Err(format!("unknown special port flag: {word}")),
+        }
+    }
+
+    Ok(port)
+}
+
+/// The tiles followed by the level info, in the layout of a DAT or SP level.
+/// Levels of other sizes keep their own width, like in MPX files. Fails with `InvalidInput`
+/// for a special port outside the level, which the header cannot point at.
+pub fn level_to_bytes(level: &Level) -> io::Result<Vec<u8>> {
+    let mut data: Vec<u8> = level.grid.array.iter().map(|tile| tile.typ.to_u8()).collect();
+    let info_start = data.len();
+    data.resize(info_start + LEVEL_INFO_SIZE, 0);
+    let info = &mut data[info_start..];
+
+    info[INFO_GRAVITY] = level.data.gravity_enabled as u8;
+
+    let name = level.data.name.as_bytes();
+    for (i, byte) in info[INFO_NAME].iter_mut().enumerate() {
+        *byte = name.get(i).copied().unwrap_or(b' ');
+    }
+
+    info[INFO_ZONKS_FROZEN] = if level.data.zonks_frozen { 2 } else { 0 };
+    info[INFO_INFOTRONS] = level.data.infotrons_required.clamp(0, 255) as u8;
+
+    let special_ports = &level.data.special_ports[..level.data.special_ports.len().min(10)];
+    info[INFO_SPECIAL_PORT_COUNT] = special_ports.len() as u8;
+    for (port, bytes) in special_ports.iter().zip(info[INFO_SPECIAL_PORTS].chunks_exact_mut(6)) {
+        let index = special_port_offset(&level.grid, port.coord).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("special port {},{} is outside the level", port.coord.x, port.coord.y),
+            )
+        })?;
+        bytes[..2].copy_from_slice(&index.to_be_bytes());
+        bytes[2] = port.gravity_enabled as u8;
+        bytes[3] = if port.zonks_frozen { 2 } else { 0 };
+        bytes[4] = port.enemies_frozen as u8;
+    }
+
+    Ok(data)
+}
+
+/// How the header points at a special port, the offset of its tile in a level of two
+/// bytes per tile.
+fn special_port_offset(grid: &Grid, coord: Coord) -> Option<u16> {
+    if !grid.contains(&coord) {
+        return None;
+    }
+    let tile = coord.y as usize * grid.width as usize + coord.x as usize;
+    u16::try_from(tile).ok()?.checked_mul(2)
+}
+
+pub fn parse_level(data: &[u8]) -> Level {
+    // tiles
+    let tiles_array = &data[..LEVEL_TILES];
+    let tiles = Grid::new(LEVEL_WIDTH, LEVEL_HEIGHT, tiles_array.to_vec());
+
+    parse_level_info(tiles, &data[LEVEL_TILES..LEVEL_SIZE])
+}
+
+fn parse_level_info(tiles: Grid, info: &[u8]) -> Level {
+    // gravity enabled
+    let gravity = info[INFO_GRAVITY];
+
+    // Name
+    let name = &info[INFO_NAME];
+
+    // zonks frozen
+    let zonk_buf = info[INFO_ZONKS_FROZEN];
+
+    // infotron count
+    let infotrons = info[INFO_INFOTRONS];
+
+    // special ports, 6 bytes each: position (big endian, doubled), gravity, zonks, enemies, unused
+    let special_port_count = (info[INFO_SPECIAL_PORT_COUNT] as usize).min(10);
+    let width = tiles.width as u16;
+    let special_ports = info[INFO_SPECIAL_PORTS]
+        .chunks_exact(6)
+        .take(special_port_count)
+        .map(|port| {
+            let index = u16::from_be_bytes([port[0], port[1]]) / 2;
+            SpecialPort {
+                coord: Coord::new((index % width) as i16, (index / width) as i16),
+                gravity_enabled: port[2] == 1,
+                zonks_frozen: port[3] == 2,
+                enemies_frozen: port[4] == 1,
+            }
+        })
+        .collect();
+
+    let gravity_enabled = gravity == 1;
+    let name = String::from_utf8_lossy(name).into_owned();
+    let zonks_frozen = zonk_buf == 2;
+
+    new_level(
+        tiles,
+        name,
+        infotrons as i32,
+        gravity_enabled,
+        zonks_frozen,
+        special_ports,
+    )
+}
+
+fn new_level(
+    grid: Grid,
+    name: String,
+    infotrons_required: i32,
+    gravity_enabled: bool,
+    zonks_frozen: bool,
+    special_ports: Vec<SpecialPort>,
+) -> Level {
+    Level {
+        data: LevelData {
+            move_priority: Vec::new(),
+            slurping: false,
+            name,
+            speed: 8.0,
+            delta_time: 0.0,
+            update_duration: Duration::ZERO,
+            camera_target: None,
+            game_state: crate::level::GameState::Active,
+            infotrons_required,
+            red_utility_disks: 0,
+            gravity_enabled,
+            zonks_frozen,
+            special_ports,
+            players: Vec::new(),
+            exit_rule: ExitRule::Any,
+        },
+        grid,
+    }
+}
+
+/// The error for a file that was read but holds something it should not, shared by every
+/// loader in the crate.
+pub(crate) fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 60x24 level, the size DAT files hold, walled in with Murphy and the exit inside.
+    fn full_size_level() -> Level {
+        let wall = "#".repeat(LEVEL_WIDTH as usize);
+        let inside = format!("#{}#", " ".repeat(LEVEL_WIDTH as usize - 2));
+        let mut rows = vec![wall.clone(), format!("#M+E{}", &inside[4..])];
+        rows.resize(LEVEL_HEIGHT as usize - 1, inside);
+        rows.push(wall);
+
+        let header = "name: WARM UP\ninfotrons: 4\ngravity: on\nzonks_frozen: on\n\
+                      special_port: 2,1 gravity enemies_frozen\n";
+        parse_level_text(&(header.to_string() + &rows.join("\n"))).unwrap()
+    }
+
+    fn port_fields(port: &SpecialPort) -> (Coord, bool, bool, bool) {
+        (port.coord, port.gravity_enabled, port.zonks_frozen, port.enemies_frozen)
+    }
+
+    fn assert_same_level(loaded: &Level, level: &Level) {
+        assert_eq!(loaded.data.name.trim_end(), level.data.name);
+        assert_eq!(loaded.data.infotrons_required, level.data.infotrons_required);
+        assert_eq!(loaded.data.gravity_enabled, level.data.gravity_enabled);
+        assert_eq!(loaded.data.zonks_frozen, level.data.zonks_frozen);
+        let ports = |level: &Level| -> Vec<_> {
+            level.data.special_ports.iter().map(port_fields).collect()
+        };
+        assert_eq!(ports(loaded), ports(level));
+        assert_eq!((loaded.grid.width, loaded.grid.height), (level.grid.width, level.grid.height));
+        assert!(loaded.grid.array.iter().zip(&level.grid.array).all(|(a, b)| a.typ == b.typ));
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn the_header_is_where_the_original_levels_have_it() {
+        let data = level_to_bytes(&full_size_level()).unwrap();
+        assert_eq!(data.len(), LEVEL_SIZE);
+        assert_eq!(data[1444], 1);
+        assert_eq!(&data[1446..1453], b"WARM UP");
+        assert_eq!(data[1469], 2);
+        assert_eq!(data[1470], 4);
+        assert_eq!(data[1471], 1);
+        // the port on tile 62, as an offset of two bytes per tile
+        assert_eq!(data[1472..1477], [0, 124, 1, 0, 1]);
+    }
+
+    #[test]
+    fn a_level_survives_a_dat_file() {
+        let level = full_size_level();
+        let path = temp_path("round-trip.dat");
+        save_level_dat(&path, &[level.clone(), level.clone()]).unwrap();
+        let loaded = load_level_dat(&path);
+        let _ = fs::remove_file(&path);
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_same_level(&loaded[1], &level);
+    }
+
+    #[test]
+    fn a_small_level_survives_an_mpx_file() {
+        let text = "name: TINY\ngravity: on\nspecial_port: 2,1\n#####\n#M+E#\n#####";
+        let level = parse_level_text(text).unwrap();
+        let path = temp_path("round-trip.mpx");
+        save_level_mpx(&path, &[full_size_level(), level.clone()]).unwrap();
+        let loaded = load_level_mpx(&path);
+        let _ = fs::remove_file(&path);
+
+        let loaded = loaded.unwrap();
+        assert_same_level(&loaded[0], &full_size_level());
+        assert_same_level(&loaded[1], &level);
+    }
+
+    #[test]
+    fn a_level_survives_its_text_form() {
+        let level = full_size_level();
+        assert_same_level(&parse_level_text(&level_to_text(&level)).unwrap(), &level);
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    level::Level,
+    level_loader::{
+        invalid_data, load_level_dat, load_level_mpx, load_level_sp, load_level_text, save_level_dat,
+        save_level_mpx, save_level_text, LEVEL_HEIGHT, LEVEL_WIDTH,
+    },
+    tile_data::tile_type::TileType,
+};
+
+pub const PACKS_DIR: &str = "levels";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PackFormat {
+    Dat,
+    Sp,
+    Mpx,
+    Text,
+}
+
+impl PackFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "dat" => Some(PackFormat::Dat),
+            "sp" => Some(PackFormat::Sp),
+            "mpx" => Some(PackFormat::Mpx),
+            "txt" => Some(PackFormat::Text),
+            _ => None,
+        }
+    }
+}
+
+/// A level file together with which of its levels have been finished, kept in a
+/// `.progress` file next to it.
+pub struct LevelPack {
+    pub name: String,
+    pub path: PathBuf,
+    pub format: PackFormat,
+    pub levels: Vec<Level>,
+    pub completed: Vec<bool>,
+}
+
+impl LevelPack {
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let format = PackFormat::from_path(&path)
+            .ok_or_else(|| io::Error::new(ErrorKind::Unsupported, "unknown level file type"))?;
+        let path_str = path.to_string_lossy();
+
+        let levels = match format {
+            PackFormat::Dat => load_level_dat(&path_str)?,
+            PackFormat::Sp => vec![load_level_sp(&path_str)?],
+            PackFormat::Mpx => load_level_mpx(&path_str)?,
+            PackFormat::Text => vec![load_level_text(&path_str)?],
+        };
+        if levels.is_empty() {
+            return Err(invalid_data("no levels"));
+        }
+
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut pack = Self {
+            name,
+            path,
+            format,
+            completed: vec![false; levels.len()],
+            levels,
+        };
+        pack.load_progress();
+
+        Ok(pack)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = self.path.to_string_lossy();
+        let fixed_size = matches!(self.format, PackFormat::Dat | PackFormat::Sp);
+        if fixed_size
+            && self
+                .levels
+                .iter()
+                .any(|level| level.grid.width != LEVEL_WIDTH || level.grid.height != LEVEL_HEIGHT)
+        {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "DAT and SP levels must be 60x24",
+            ));
+        }
+        // level files have no byte for these, they would turn into other tiles
+        if self.format != PackFormat::Text {
+            let text_only = self
+                .levels
+                .iter()
+                .flat_map(|level| &level.grid.array)
+                .find(|tile| TileType::TEXT_ONLY.contains(&tile.typ));
+            if let Some(tile) = text_only {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{:?} can only be saved in text levels", tile.typ),
+                ));
+            }
+        }
+
+        match self.format {
+            PackFormat::Dat => save_level_dat(&path, &self.levels),
+            PackFormat::Sp => save_level_dat(&path, &self.levels[..1]),
+            PackFormat::Mpx => save_level_mpx(&path, &self.levels),
+            PackFormat::Text => save_level_text(&path, &self.levels[0]),
+        }
+    }
+
+    pub fn completed_count(&self) -> usize {
+        self.completed.iter().filter(|completed| **completed).count()
+    }
+
+    pub fn first_uncompleted(&self) -> usize {
+        self.completed
+            .iter()
+            .position(|completed| !completed)
+            .unwrap_or(0)
+    }
+
+    pub fn mark_completed(&mut self, level: usize) -> io::Result<()> {
+        if self.completed[level] {
+            return Ok(());
+        }
+        self.completed[level] = true;
+
+        let progress: String = self
+            .completed
+            .iter()
+            .map(|completed| if *completed { '1' } else { '0' })
+            .collect();
+        fs::write(self.progress_path(), progress)
+    }
+
+    fn progress_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".progress");
+        path.into()
+    }
+
+    fn load_progress(&mut self) {
+        let Ok(progress) = fs::read_to_string(self.progress_path()) else {
+            return;
+        };
+        for (completed, c) in self.completed.iter_mut().zip(progress.trim().chars()) {
+            *completed = c == '1';
+        }
+    }
+}
+
+/// Every level file in `dir`, sorted by name. Files that fail to load are reported and skipped.
+pub fn scan_packs(dir: impl AsRef<Path>) -> Vec<LevelPack> {
+    let Ok(entries) = fs::read_dir(dir.as_ref()) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && PackFormat::from_path(path).is_some())
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| match LevelPack::load(&path) {
+            Ok(pack) => Some(pack),
+            Err(err) => {
+                eprintln!("{}: {err}", path.display());
+                None
+            }
+        })
+        .collect()
+}
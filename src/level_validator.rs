@@ -0,0 +1,181 @@
+use std::fmt;
+
+use crate::{
+    grid::Coord,
+    level::Level,
+    tile_data::tile_type::TileType,
+};
+
+#[derive(Clone, Debug)]
+pub enum LevelIssue {
+    NoMurphy,
+    MultipleMurphys(usize),
+    NoExit,
+    NotEnoughInfotrons { required: i32, available: usize },
+    BorderNotHardware { coord: Coord, typ: TileType },
+    UnknownTile { coord: Coord, byte: u8 },
+    SpecialPortOutOfBounds(Coord),
+    SpecialPortNotOnPort { coord: Coord, typ: TileType },
+}
+
+impl fmt::Display for LevelIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelIssue::NoMurphy => write!(f, "no Murphy"),
+            LevelIssue::MultipleMurphys(count) => write!(f, "{count} Murphys"),
+            LevelIssue::NoExit => write!(f, "no Exit"),
+            LevelIssue::NotEnoughInfotrons {
+                required,
+                available,
+            } => write!(
+                f,
+                "{required} infotrons required but only {available} infotrons and electrons present"
+            ),
+            LevelIssue::BorderNotHardware { coord, typ } => {
+                write!(f, "border tile {:?} at ({}, {}) is not hardware", typ, coord.x, coord.y)
+            }
+            LevelIssue::UnknownTile { coord, byte } => {
+                write!(f, "unknown tile byte {byte} at ({}, {})", coord.x, coord.y)
+            }
+            LevelIssue::SpecialPortOutOfBounds(coord) => {
+                write!(f, "special port at ({}, {}) is outside the level", coord.x, coord.y)
+            }
+            LevelIssue::SpecialPortNotOnPort { coord, typ } => write!(
+                f,
+                "special port at ({}, {}) points at {:?} instead of a port",
+                coord.x, coord.y, typ
+            ),
+        }
+    }
+}
+
+pub fn validate_level(level: &Level) -> Vec<LevelIssue> {
+    let grid = &level.grid;
+    let mut issues = Vec::new();
+
+    let mut murphys = 0;
+    let mut exits = 0;
+    let mut infotrons = 0;
+
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let coord = Coord::new(x, y);
+            let typ = grid.get(&coord).typ;
+            match typ {
+                TileType::Murphy => murphys += 1,
+                TileType::Exit => exits += 1,
+                TileType::Infotron | TileType::Electron => infotrons += 1,
+                _ => {}
+            }
+
+            let on_border = x == 0 || y == 0 || x == grid.width - 1 || y == grid.height - 1;
+            if on_border && !typ.is_hardware() {
+                issues.push(LevelIssue::BorderNotHardware { coord, typ });
+            }
+        }
+    }
+
+    match murphys {
+        0 => issues.push(LevelIssue::NoMurphy),
+        1 => {}
+        count => issues.push(LevelIssue::MultipleMurphys(count)),
+    }
+
+    if exits == 0 {
+        issues.push(LevelIssue::NoExit);
+    }
+
+    if level.data.infotrons_required > infotrons as i32 {
+        issues.push(LevelIssue::NotEnoughInfotrons {
+            required: level.data.infotrons_required,
+            available: infotrons,
+        });
+    }
+
+    for port in &level.data.special_ports {
+        let coord = port.coord;
+        if !grid.contains(&coord) {
+            issues.push(LevelIssue::SpecialPortOutOfBounds(coord));
+            continue;
+        }
+        let typ = grid.get(&coord).typ;
+        if !typ.is_port() {
+            issues.push(LevelIssue::SpecialPortNotOnPort { coord, typ });
+        }
+    }
+
+    issues
+}
+
+/// `TileType::from_u8` maps unknown bytes to `Empty`, so those can only be found in the raw tiles.
+pub fn validate_tile_bytes(bytes: &[u8], width: i16) -> Vec<LevelIssue> {
+    bytes
+        .iter()
+        .enumerate()
+        .filter(|(_, byte)| TileType::try_from_u8(**byte).is_none())
+        .map(|(i, byte)| LevelIssue::UnknownTile {
+            coord: Coord::new(i as i16 % width, i as i16 / width),
+            byte: *byte,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{level::SpecialPort, level_loader::parse_level_text};
+
+    #[test]
+    fn a_playable_level_has_no_issues() {
+        let level = parse_level_text("infotrons: 1\n######\n#M*E #\n######").unwrap();
+        assert!(validate_level(&level).is_empty());
+    }
+
+    #[test]
+    fn missing_murphy_exit_and_infotrons_are_reported() {
+        let level = parse_level_text("infotrons: 2\n#####\n#*  #\n#####").unwrap();
+        let issues = validate_level(&level);
+        assert!(matches!(issues[..], [
+            LevelIssue::NoMurphy,
+            LevelIssue::NoExit,
+            LevelIssue::NotEnoughInfotrons { required: 2, available: 1 },
+        ]));
+    }
+
+    #[test]
+    fn an_open_border_is_reported() {
+        let level = parse_level_text("#####\n M E#\n#####").unwrap();
+        let issues = validate_level(&level);
+        assert!(matches!(issues[..], [LevelIssue::BorderNotHardware {
+            coord: Coord { x: 0, y: 1 },
+            typ: TileType::Empty,
+        }]));
+    }
+
+    #[test]
+    fn special_ports_must_be_ports_inside_the_level() {
+        let mut level = parse_level_text("#####\n#M.E#\n#####").unwrap();
+        for coord in [Coord::new(2, 1), Coord::new(9, 9)] {
+            level.data.special_ports.push(SpecialPort {
+                coord,
+                gravity_enabled: false,
+                zonks_frozen: false,
+                enemies_frozen: false,
+            });
+        }
+        let issues = validate_level(&level);
+        assert!(matches!(issues[..], [
+            LevelIssue::SpecialPortNotOnPort { typ: TileType::Base, .. },
+            LevelIssue::SpecialPortOutOfBounds(Coord { x: 9, y: 9 }),
+        ]));
+    }
+
+    #[test]
+    fn unknown_tile_bytes_are_found_with_their_position() {
+        let issues = validate_tile_bytes(&[6, 0, 3, 6, 200, 6], 3);
+        assert!(matches!(issues[..], [LevelIssue::UnknownTile {
+            coord: Coord { x: 1, y: 1 },
+            byte: 200,
+        }]));
+    }
+}
@@ -0,0 +1,45 @@
+//! The Supaplex engine: levels, tiles and the systems that simulate them, with loaders for
+//! the level file formats. `Level::tick` only needs the level itself and runs without a
+//! window, the game binary is a macroquad frontend on top. A few helpers do use macroquad's
+//! window state and must only be called from a running game: `Level::update` and
+//! `KeySet::read` read the keyboard and frame time, `Level::draw_text`, `Level::draw_info`
+//! and `Level::draw` onto a `Texture2D` draw to the screen. `png_export` draws levels
+//! without a window.
+//!
+//! ```no_run
+//! use supaplex::{level_pack::LevelPack, replay::REPLAY_DELTA_TIME, GameState, TileMove};
+//!
+//! let mut level = LevelPack::load("LEVELS.DAT").unwrap().levels.remove(0);
+//! level.data.move_priority.push(TileMove::Right);
+//! while level.data.game_state == GameState::Active {
+//!     level.tick(REPLAY_DELTA_TIME);
+//! }
+//! ```
+
+pub mod bot;
+pub mod danger;
+pub mod env;
+pub mod grid;
+pub mod json;
+pub mod level;
+pub mod level_loader;
+pub mod level_pack;
+pub mod level_validator;
+pub mod netplay;
+pub mod pathfinding;
+pub mod png_export;
+pub mod replay;
+pub mod rewind;
+pub mod save_state;
+#[cfg(test)]
+mod scenario;
+pub mod schedule;
+pub mod solver;
+pub mod tile_data;
+
+pub use grid::{Coord, Grid};
+pub use level::{GameState, Level, LevelData};
+pub use tile_data::{
+    tile::Tile, tile_interaction::TileInteraction, tile_move::TileMove, tile_state::TileState,
+    tile_type::TileType,
+};
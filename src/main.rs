@@ -1,34 +1,88 @@
-mod grid;
-mod tile_data;
-mod level;
+mod cli;
+mod console;
+mod editor;
+mod menu;
+mod overlay;
 mod pixel_camera;
-mod level_loader;
+mod tui;
 
-use level::Level;
-use level_loader::load_level_dat;
+use std::{fs, io::ErrorKind, path::Path, process::ExitCode};
+
+use cli::{parse_args, Command, PlayArgs, USAGE};
+use console::Console;
+use editor::{Editor, EditorAction};
 use macroquad::{prelude::*, miniquad::gl::GL_MULTISAMPLE};
+use menu::Menu;
 use pixel_camera::PixelCamera;
+use supaplex::{
+    bot::{BotServer, BotSession},
+    danger::{predict_danger, DANGER_TICKS},
+    grid::Coord,
+    level::{ExitRule, GameState, Level, StartOverride},
+    level_loader::{level_to_text, LEVEL_SIZE, LEVEL_TILES, LEVEL_WIDTH},
+    level_pack::{scan_packs, LevelPack, PackFormat, PACKS_DIR},
+    level_validator::{validate_level, validate_tile_bytes, LevelIssue},
+    netplay::{NetMode, Netplay},
+    pathfinding::{hint_route, Walk, WalkStep},
+    png_export::{load_sprite_sheet, render_level, save_png},
+    replay::{load_replay, save_replay, Replay, ReplayPlayer, ReplayStep, REPLAY_DELTA_TIME},
+    rewind::{RewindBuffer, REWIND_CAPACITY},
+    save_state::{load_state, save_state, slot_path, SaveState, QUICK_SLOT, SAVE_SLOTS},
+    solver::{solve, SolveError},
+    tile_data::{sprite::DEFAULT_SPRITE_SHEET, tile_move::TileMove},
+};
+use tui::run_tui;
 
 
 struct Game {
     camera: PixelCamera,
     freecam: Option<Vec2>,
     current_level: Level,
-    level_set: Vec<Level>,
+    packs: Vec<LevelPack>,
+    selected_pack: usize,
+    pack_menu: Menu,
+    slot_menu: Menu,
+    save_slots: Vec<String>,
     selected_level: usize,
+    start_override: Option<StartOverride>,
+    replay: Option<ReplayPlayer>,
+    rewind: RewindBuffer,
+    bot: Option<(BotServer, BotSession)>,
+    /// Set once a bot has sent a command, the level then only moves when a bot steps it.
+    bot_driven: bool,
+    netplay: Option<Netplay>,
+    /// Set with `--coop`, levels with two Murphys are then played by two people.
+    coop: Option<ExitRule>,
+    split_screen: bool,
+    editor: Option<Editor>,
     tiles: Texture2D,
     text_params: TextParams,
     debug_info: bool,
     fullscreen: bool,
+    level_issues: Vec<LevelIssue>,
+    /// Set with `--click-move` or M, clicking a tile then walks Murphy there.
+    click_move: bool,
+    walk: Option<Walk>,
+    /// Toggled with H for practice, shows the way to the next infotron or the exit.
+    hints: bool,
+    /// Toggled with X, shades the tiles that will be hit if Murphy stands still.
+    danger: bool,
+    console: Console,
+    /// Set while the running level did not start like the pack level, through a start
+    /// override or a console command. Finishing it then does not count as completed.
+    cheated: bool,
+    /// Toggled with F, the level then only moves one tick per press of ], [ steps back.
+    paused: bool,
+    /// The tiles that changed in the last step while paused.
+    step_changes: Vec<Coord>,
 }
 
 impl Game {
-    async fn new() -> Self {
+    async fn new(args: PlayArgs, startup: Startup) -> Self {
         //let level = Level::new(8.0, 60, 24, LEVEL_EASY_DEAL.to_vec());
 
-        let levels = load_level_dat("LEVELS.DAT").unwrap();
-
-        let tiles = load_texture("assets/moving2.png").await.unwrap();
+        let sprites = args.sprites.as_deref().unwrap_or(DEFAULT_SPRITE_SHEET);
+        let tiles = load_texture(sprites).await.unwrap();
         tiles.set_filter(FilterMode::Nearest);
 
         let font = load_ttf_font("assets/FiraSans-Medium.ttf").await.unwrap();
@@ -39,28 +93,405 @@ impl Game {
             ..Default::default()
         };
 
-        let camera = PixelCamera::new(Vec2::ZERO, 1.0, 256.0);
+        let zoom = args.scale.unwrap_or(1) as f32;
+        let camera = PixelCamera::new(Vec2::ZERO, zoom, 256.0);
+        let coop = args.coop_exit_rule().filter(|_| startup.netplay.is_none());
+        let mut current_level = startup.current_level;
+        add_coop_players(coop, &mut current_level);
 
         Self {
             camera,
             freecam: None,
-            current_level: levels[0].clone(),
-            level_set: levels,
-            selected_level: 0,
+            level_issues: validate_level(&startup.packs[0].levels[startup.selected_level]),
+            current_level,
+            packs: startup.packs,
+            selected_pack: 0,
+            pack_menu: Menu::default(),
+            slot_menu: Menu::default(),
+            save_slots: Vec::new(),
+            selected_level: startup.selected_level,
+            start_override: args.start,
+            replay: startup.replay.map(ReplayPlayer::new),
+            rewind: RewindBuffer::new(REWIND_CAPACITY),
+            bot: startup
+                .bot
+                .map(|server| (server, BotSession::new(startup.selected_level))),
+            bot_driven: false,
+            netplay: startup.netplay,
+            coop,
+            split_screen: false,
+            editor: None,
             tiles,
             text_params,
             debug_info: false,
-            fullscreen: false
+            fullscreen: false,
+            click_move: args.click_move,
+            walk: None,
+            hints: false,
+            danger: false,
+            console: Console::default(),
+            cheated: args.start.is_some(),
+            paused: false,
+            step_changes: Vec::new(),
+        }
+    }
+
+    fn level_set(&self) -> &[Level] {
+        &self.packs[self.selected_pack].levels
+    }
+}
+
+/// What the command line asked to start with, loaded before any window is opened so
+/// mistakes are reported on the terminal.
+struct Startup {
+    packs: Vec<LevelPack>,
+    selected_level: usize,
+    current_level: Level,
+    replay: Option<Replay>,
+    bot: Option<BotServer>,
+    netplay: Option<Netplay>,
+}
+
+fn startup(args: &PlayArgs) -> Result<Startup, String> {
+    let mut packs = match &args.levels {
+        Some(path) => vec![LevelPack::load(path).map_err(|err| format!("{path}: {err}"))?],
+        // only a missing LEVELS.DAT is fine, a broken one is reported
+        None => match LevelPack::load("LEVELS.DAT") {
+            Ok(pack) => vec![pack],
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(format!("LEVELS.DAT: {err}")),
+        },
+    };
+    packs.extend(scan_packs(PACKS_DIR));
+    if packs.is_empty() {
+        return Err(format!("no LEVELS.DAT and no levels in {PACKS_DIR}/"));
+    }
+
+    let replay = match &args.replay {
+        Some(path) => Some(load_replay(path).map_err(|err| format!("{path}: {err}"))?),
+        None => None,
+    };
+    if let Some(sprites) = &args.sprites {
+        if !Path::new(sprites).is_file() {
+            return Err(format!("{sprites}: sprite sheet not found"));
+        }
+    }
+
+    let bot = match &args.listen {
+        Some(address) => {
+            Some(BotServer::listen(address).map_err(|err| format!("{address}: {err}"))?)
+        }
+        None => None,
+    };
+
+    let levels = &packs[0].levels;
+    let selected_level = args
+        .level
+        .or(replay.as_ref().map(|replay| replay.level))
+        .unwrap_or(0);
+    if selected_level >= levels.len() {
+        return Err(format!("level must be between 1 and {}", levels.len()));
+    }
+    let level = &levels[selected_level];
+    let current_level = match &args.start {
+        Some(start) => {
+            if let Some(coord) = start.murphy.filter(|coord| !level.grid.contains(coord)) {
+                let (x, y) = (coord.x, coord.y);
+                return Err(format!("--murphy {x},{y}: murphy tile outside the level"));
+            }
+            level.with_start(start)
+        }
+        None => level.clone(),
+    };
+
+    let netplay = match (&args.host, &args.join) {
+        (Some(address), _) => {
+            eprintln!("waiting for a player to join on {address}");
+            let mode = args.coop_exit_rule().map_or(NetMode::Versus, NetMode::Coop);
+            let netplay = Netplay::host(address, &current_level, mode);
+            Some(netplay.map_err(|err| format!("{address}: {err}"))?)
+        }
+        (None, Some(address)) => Some(
+            Netplay::join(address, &current_level).map_err(|err| format!("{address}: {err}"))?,
+        ),
+        (None, None) => None,
+    };
+
+    Ok(Startup {
+        packs,
+        selected_level,
+        current_level,
+        replay,
+        bot,
+        netplay,
+    })
+}
+
+fn window_conf(args: &PlayArgs) -> Conf {
+    let mut conf = Conf {
+        window_title: "Supaplex".to_string(),
+        ..Default::default()
+    };
+    if let Some(scale) = args.scale {
+        conf.window_width *= scale as i32;
+        conf.window_height *= scale as i32;
+    }
+    conf
+}
+
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Validate { path }) => validate_pack(&path),
+        Ok(Command::Solve {
+            path,
+            level,
+            output,
+        }) => solve_level(&path, level, output.as_deref()),
+        Ok(Command::ExportPng {
+            path,
+            level,
+            output,
+            sprites,
+        }) => export_png(&path, level, &output, sprites.as_deref().unwrap_or(DEFAULT_SPRITE_SHEET)),
+        Ok(Command::Tui(args)) => play_tui(args),
+        Ok(Command::Headless(args)) => run_headless(args),
+        Ok(Command::Play(args)) => match startup(&args) {
+            Ok(startup) => {
+                macroquad::Window::from_config(window_conf(&args), run(args, startup));
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                ExitCode::FAILURE
+            }
+        },
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn validate_pack(path: &str) -> ExitCode {
+    let pack = match LevelPack::load(path) {
+        Ok(pack) => pack,
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // unknown tile bytes are only visible in the raw file
+    let data = match pack.format {
+        PackFormat::Dat | PackFormat::Sp => fs::read(path).unwrap_or_default(),
+        PackFormat::Mpx | PackFormat::Text => Vec::new(),
+    };
+
+    let mut issue_count = 0;
+    for (i, level) in pack.levels.iter().enumerate() {
+        let mut issues = match data.get(i * LEVEL_SIZE..i * LEVEL_SIZE + LEVEL_TILES) {
+            Some(tiles) => validate_tile_bytes(tiles, LEVEL_WIDTH),
+            None => Vec::new(),
+        };
+        issues.extend(validate_level(level));
+
+        for issue in &issues {
+            println!("{:03} {}: {issue}", i + 1, level.data.name.trim());
         }
+        issue_count += issues.len();
+    }
+
+    if pack.format == PackFormat::Dat && data.len() % LEVEL_SIZE != 0 {
+        println!("{path}: {} trailing bytes", data.len() % LEVEL_SIZE);
+        issue_count += 1;
+    }
+
+    println!("{issue_count} issues found");
+    if issue_count == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
     }
 }
 
-#[macroquad::main("Supaplex")]
-async fn main() {
+const SOLVER_MAX_STATES: usize = 200_000;
+
+fn solve_level(path: &str, index: usize, output: Option<&str>) -> ExitCode {
+    let levels = match LevelPack::load(path) {
+        Ok(pack) => pack.levels,
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if index >= levels.len() {
+        eprintln!("level must be between 1 and {}", levels.len());
+        return ExitCode::FAILURE;
+    }
+
+    match solve(&levels[index], index, SOLVER_MAX_STATES) {
+        Ok(replay) => {
+            let mut check = levels[index].clone();
+            replay.play(&mut check);
+            println!(
+                "solved in {} steps, replay {}",
+                replay.steps.len(),
+                if check.data.game_state == GameState::Finished { "verified" } else { "does not finish" }
+            );
+            match output {
+                Some(output) => {
+                    if let Err(err) = save_replay(output, &replay) {
+                        eprintln!("{output}: {err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+                None => print!("{replay}"),
+            }
+            ExitCode::SUCCESS
+        }
+        Err(SolveError::Unsolvable { explored }) => {
+            println!("unsolvable, explored {explored} states");
+            ExitCode::FAILURE
+        }
+        Err(SolveError::LimitReached { explored }) => {
+            println!("no solution found within {explored} states");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn play_tui(args: PlayArgs) -> ExitCode {
+    let result = startup(&args).and_then(|startup| {
+        run_tui(&startup.current_level).map_err(|err| err.to_string())
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn export_png(path: &str, index: usize, output: &str, sprites: &str) -> ExitCode {
+    let levels = match LevelPack::load(path) {
+        Ok(pack) => pack.levels,
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let Some(level) = levels.get(index) else {
+        eprintln!("level must be between 1 and {}", levels.len());
+        return ExitCode::FAILURE;
+    };
+
+    let result = load_sprite_sheet(sprites)
+        .and_then(|sheet| render_level(level, &sheet))
+        .and_then(|image| save_png(output, &image));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{output}: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs without a window for `--ticks` ticks, or as long as the replay lasts, then prints
+/// the final state.
+fn run_headless(args: PlayArgs) -> ExitCode {
+    let startup = match startup(&args) {
+        Ok(startup) => startup,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(netplay) = startup.netplay {
+        return run_netplay(&args, netplay, startup.replay.map(ReplayPlayer::new));
+    }
+    let mut level = startup.current_level;
+    if let Some(server) = startup.bot {
+        serve_bots(&server, level, &startup.packs[0].levels, startup.selected_level);
+        return ExitCode::SUCCESS;
+    }
+    let mut player = startup.replay.map(ReplayPlayer::new);
+
+    let Some(ticks) = args.ticks.or(player.as_ref().map(ReplayPlayer::total_ticks)) else {
+        eprintln!("--headless needs --ticks or --replay\n{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    for _ in 0..ticks {
+        match &mut player {
+            Some(player) => player.tick(&mut level),
+            None => level.tick(REPLAY_DELTA_TIME),
+        }
+    }
+
+    write_dump(&args, "", ticks, &level)
+}
+
+/// Plays the replay's inputs against the other player until the game is over or `--ticks`
+/// ran out, then prints this player's level.
+fn run_netplay(args: &PlayArgs, mut netplay: Netplay, mut player: Option<ReplayPlayer>) -> ExitCode {
+    while !netplay.game_over() && args.ticks.is_none_or(|ticks| netplay.tick() < ticks) {
+        let input = player
+            .as_mut()
+            .map_or(ReplayStep::new(TileMove::None), ReplayPlayer::next_step);
+        if let Err(err) = netplay.step(input) {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let winner = match netplay.winner() {
+        Some(seat) => (seat + 1).to_string(),
+        None => "none".to_string(),
+    };
+    let header = format!("seat: {}\nwinner: {winner}\n", netplay.seat + 1);
+    let exit_code = write_dump(args, &header, netplay.tick(), netplay.level(netplay.seat));
+    if let Err(err) = netplay.close() {
+        eprintln!("{err}");
+    }
+    exit_code
+}
+
+fn write_dump(args: &PlayArgs, header: &str, ticks: usize, level: &Level) -> ExitCode {
+    let dump = format!(
+        "{header}ticks: {ticks}\ngame_state: {:?}\nred_disks: {}\n{}",
+        level.data.game_state,
+        level.data.red_utility_disks,
+        level_to_text(level)
+    );
+    match &args.dump {
+        Some(path) => {
+            if let Err(err) = fs::write(path, dump) {
+                eprintln!("{path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{dump}"),
+    }
+    ExitCode::SUCCESS
+}
+
+/// Lets bots drive the level without a window, nothing moves between their commands.
+fn serve_bots(server: &BotServer, mut level: Level, levels: &[Level], selected_level: usize) {
+    let mut session = BotSession::new(selected_level);
+    let mut events = Vec::new();
+    while let Some((client, command)) = server.recv() {
+        let result =
+            command.and_then(|command| session.run(command, &mut level, levels, &mut events));
+        server.respond(client, result, &events);
+        events.clear();
+    }
+}
+
+async fn run(args: PlayArgs, startup: Startup) {
     unsafe {
         miniquad::gl::glDisable(GL_MULTISAMPLE);
     }
-    let mut game = Game::new().await;
+    let mut game = Game::new(args, startup).await;
 
     loop {
         update(&mut game);
@@ -70,16 +501,95 @@ async fn main() {
         render(&mut game);
         set_default_camera();
 
-        game.current_level.draw_info(game.text_params);
+        match &game.editor {
+            Some(editor) if !editor.playtesting => editor.draw_ui(game.text_params, game.tiles),
+            _ => game.current_level.draw_info(game.text_params),
+        }
+        if let Some(netplay) = &game.netplay {
+            render_netplay(netplay, game.text_params);
+        }
         if game.debug_info {
             render_ui(&game);
         }
+        if game.paused {
+            render_paused(&game);
+        }
+        if game.console.open {
+            game.console.draw(game.text_params);
+        }
+        if game.pack_menu.open {
+            render_pack_menu(&game);
+        }
+        if game.slot_menu.open {
+            game.slot_menu.draw(
+                "Save slots (Enter to load, S to save)",
+                &game.save_slots,
+                game.text_params,
+            );
+        }
 
         next_frame().await
     }
 }
 
 fn update(game: &mut Game) {
+    if game.netplay.is_some() {
+        update_netplay(game);
+        return;
+    }
+
+    if is_key_pressed(KeyCode::Tab) && game.editor.is_none() {
+        game.pack_menu.open = !game.pack_menu.open;
+        game.pack_menu.selected = game.selected_pack;
+    }
+    if game.pack_menu.open {
+        if let Some(pack) = game.pack_menu.update(game.packs.len()) {
+            game.pack_menu.open = false;
+            select_pack(game, pack);
+        }
+        return;
+    }
+
+    if game.editor.is_none() {
+        if is_key_pressed(KeyCode::F6) {
+            game.slot_menu.open = !game.slot_menu.open;
+            game.save_slots = (0..SAVE_SLOTS).map(slot_summary).collect();
+        }
+        if game.slot_menu.open {
+            update_slot_menu(game);
+            return;
+        }
+        if is_key_pressed(KeyCode::F5) {
+            save_slot(game, QUICK_SLOT);
+        }
+        if is_key_pressed(KeyCode::F9) {
+            load_slot(game, QUICK_SLOT);
+        }
+    }
+
+    if let Some(editor) = &mut game.editor {
+        if !editor.playtesting {
+            update_editor(game);
+            return;
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            editor.playtesting = false;
+            return;
+        }
+    }
+
+    // the level waits while a debug command is typed
+    if game.console.open {
+        if game.console.update(&mut game.current_level) {
+            game.cheated = true;
+        }
+        return;
+    }
+    if game.debug_info && is_key_pressed(KeyCode::GraveAccent) {
+        game.console.open = true;
+        return;
+    }
+
     if let Some(pos) = &mut game.freecam {
         game.camera.move_camera_with_keys(KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D);
         *pos = game.camera.position;
@@ -95,9 +605,23 @@ fn update(game: &mut Game) {
     game.current_level.data.speed += speed;
 
     if is_key_pressed(KeyCode::R) {
-        game.current_level = game.level_set[game.selected_level].clone();
+        match &game.editor {
+            Some(editor) => {
+                game.current_level = editor.playtest_level();
+                add_coop_players(game.coop, &mut game.current_level);
+                game.rewind.reset(&game.current_level);
+            }
+            None => restart_level(game),
+        }
     }
-    if is_key_pressed(KeyCode::P) {
+    // the free camera moves with WASD, which the second player needs
+    let coop_level = !game.current_level.data.players.is_empty();
+    if coop_level {
+        game.freecam = None;
+        if is_key_pressed(KeyCode::V) {
+            game.split_screen = !game.split_screen;
+        }
+    } else if is_key_pressed(KeyCode::P) {
         if game.freecam.is_none() {
             game.freecam = Some(game.camera.position);
         } else {
@@ -105,46 +629,487 @@ fn update(game: &mut Game) {
         }
     }
 
-    if is_key_pressed(KeyCode::Comma) {
-        game.selected_level -= 1;
-        game.current_level = game.level_set[game.selected_level].clone();
+    if is_key_pressed(KeyCode::E) && game.editor.is_none() {
+        game.editor = Some(Editor::new(game.level_set()[game.selected_level].clone()));
+        return;
     }
-    if is_key_pressed(KeyCode::Period) {
-        if game.selected_level < game.level_set.len() {
-            game.selected_level += 1;
-        } else {
-            game.selected_level = 0;
-        }
-        game.current_level = game.level_set[game.selected_level].clone();
+
+    if is_key_pressed(KeyCode::Comma) && game.editor.is_none() {
+        let level = game.selected_level.checked_sub(1).unwrap_or(game.level_set().len() - 1);
+        select_level(game, level);
+    }
+    if is_key_pressed(KeyCode::Period) && game.editor.is_none() {
+        let level = (game.selected_level + 1) % game.level_set().len();
+        select_level(game, level);
     }
 
     if is_key_pressed(KeyCode::T) {
         game.debug_info = !game.debug_info;
     }
 
+    if game.debug_info && game.editor.is_none() {
+        update_start_override(game);
+    }
+
+    if is_key_pressed(KeyCode::H) {
+        game.hints = !game.hints;
+    }
+
+    if is_key_pressed(KeyCode::X) {
+        game.danger = !game.danger;
+    }
+
+    if is_key_pressed(KeyCode::F) {
+        game.paused = !game.paused;
+        game.step_changes.clear();
+    }
+
+    if is_key_pressed(KeyCode::M) {
+        game.click_move = !game.click_move;
+        game.walk = None;
+    }
+
     if is_key_pressed(KeyCode::F11) {
         game.fullscreen = !game.fullscreen;
         macroquad::window::set_fullscreen(game.fullscreen);
     }
 
-    game.current_level.update();
+    // hold Z to step back through the recorded ticks
+    if is_key_down(KeyCode::Z) {
+        game.current_level.data.move_priority.clear();
+        game.walk = None;
+        game.rewind.rewind(&mut game.current_level);
+        return;
+    }
+
+    let bot_stepped = update_bot(game);
+    if game.bot_driven {
+        if bot_stepped {
+            game.rewind.record(&game.current_level);
+        }
+    } else if game.paused {
+        update_frame_step(game);
+    } else {
+        match &mut game.replay {
+            Some(player) if !player.finished() => player.tick(&mut game.current_level),
+            _ if game.click_move && game.current_level.data.players.is_empty() => {
+                update_click_move(game)
+            }
+            _ => game.current_level.update(),
+        }
+        game.rewind.record(&game.current_level);
+    }
+
+    if game.current_level.data.game_state == GameState::Finished
+        && game.editor.is_none()
+        && !game.cheated
+    {
+        let pack = &mut game.packs[game.selected_pack];
+        if let Err(err) = pack.mark_completed(game.selected_level) {
+            eprintln!("{}: {err}", pack.path.display());
+        }
+    }
+}
+
+/// One lockstep tick per frame, like a replay being watched, with the held arrow keys as
+/// this player's input. Level switching, the editor and rewind would break the lockstep, so
+/// none of them are available.
+fn update_netplay(game: &mut Game) {
+    game.camera.handle_zoom();
+    let Some(netplay) = &mut game.netplay else {
+        return;
+    };
+    if netplay.game_over() {
+        return;
+    }
+
+    let mov = [
+        (KeyCode::Up, TileMove::Up),
+        (KeyCode::Right, TileMove::Right),
+        (KeyCode::Down, TileMove::Down),
+        (KeyCode::Left, TileMove::Left),
+    ]
+    .into_iter()
+    .find(|(key, _)| is_key_down(*key))
+    .map_or(TileMove::None, |(_, mov)| mov);
+    let input = ReplayStep {
+        mov,
+        slurping: is_key_down(KeyCode::Space),
+    };
+
+    if is_key_pressed(KeyCode::V) {
+        game.split_screen = !game.split_screen;
+    }
+    match netplay.step(input) {
+        Ok(()) => game.current_level = netplay.level(netplay.seat).clone(),
+        Err(err) => {
+            eprintln!("netplay: {err}");
+            game.netplay = None;
+        }
+    }
+}
+
+/// Runs the commands bots sent since the last frame, returns whether one changed the level.
+fn update_bot(game: &mut Game) -> bool {
+    let Some((server, session)) = &mut game.bot else {
+        return false;
+    };
+    // the level may have been changed from the keyboard since the last command
+    session.level_index = game.selected_level;
+
+    let levels = &game.packs[game.selected_pack].levels;
+    let mut changed = false;
+    let mut events = Vec::new();
+    while let Some((client, command)) = server.try_recv() {
+        game.bot_driven = true;
+        let result = command.and_then(|command| {
+            changed = true;
+            session.run(command, &mut game.current_level, levels, &mut events)
+        });
+        server.respond(client, result, &events);
+        events.clear();
+    }
+
+    if session.level_index != game.selected_level {
+        game.selected_level = session.level_index;
+        game.start_override = None;
+        game.replay = None;
+        game.level_issues = validate_level(&game.current_level);
+        game.rewind.reset(&game.current_level);
+    }
+    changed
+}
+
+fn update_editor(game: &mut Game) {
+    let Some(editor) = &mut game.editor else {
+        return;
+    };
+
+    match editor.update(&mut game.camera) {
+        EditorAction::None => {}
+        EditorAction::Playtest => {
+            game.current_level = editor.playtest_level();
+            add_coop_players(game.coop, &mut game.current_level);
+            game.rewind.reset(&game.current_level);
+            game.freecam = None;
+            editor.playtesting = true;
+        }
+        EditorAction::Save => {
+            let pack = &mut game.packs[game.selected_pack];
+            pack.levels[game.selected_level] = editor.level.clone();
+            if let Err(err) = pack.save() {
+                eprintln!("{}: {err}", pack.path.display());
+            }
+        }
+        EditorAction::Exit => {
+            game.editor = None;
+            select_level(game, game.selected_level);
+        }
+    }
+}
+
+fn select_pack(game: &mut Game, pack: usize) {
+    game.selected_pack = pack;
+    game.start_override = None;
+    let level = game.packs[pack].first_uncompleted();
+    select_level(game, level);
+}
+
+fn select_level(game: &mut Game, level: usize) {
+    if level != game.selected_level {
+        game.start_override = None;
+        game.replay = None;
+    }
+    game.selected_level = level;
+    game.level_issues = validate_level(&game.level_set()[level]);
+    restart_level(game);
+}
+
+fn restart_level(game: &mut Game) {
+    let level = &game.level_set()[game.selected_level];
+    game.current_level = match &game.start_override {
+        Some(start) => level.with_start(start),
+        None => level.clone(),
+    };
+    game.cheated = game.start_override.is_some();
+    add_coop_players(game.coop, &mut game.current_level);
+    if let Some(player) = &mut game.replay {
+        player.restart();
+    }
+    game.rewind.reset(&game.current_level);
+    game.walk = None;
+}
+
+/// Hands every Murphy to a player in co-op, levels with a single Murphy stay single player.
+fn add_coop_players(coop: Option<ExitRule>, level: &mut Level) {
+    if let Some(exit_rule) = coop {
+        if level.add_players(exit_rule) < 2 {
+            level.data.players.clear();
+        }
+    }
+}
+
+/// Enter loads the selected slot, S saves the running level into it.
+fn update_slot_menu(game: &mut Game) {
+    if let Some(slot) = game.slot_menu.update(SAVE_SLOTS) {
+        if load_slot(game, slot) {
+            game.slot_menu.open = false;
+        }
+    }
+    if is_key_pressed(KeyCode::S) {
+        let slot = game.slot_menu.selected;
+        save_slot(game, slot);
+        game.save_slots[slot] = slot_summary(slot);
+    }
+}
+
+fn save_slot(game: &Game, slot: usize) {
+    let state = SaveState {
+        pack_path: game.packs[game.selected_pack].path.to_string_lossy().into_owned(),
+        level_index: game.selected_level,
+        level: game.current_level.clone(),
+    };
+    let path = slot_path(slot);
+    if let Err(err) = save_state(&path, &state) {
+        eprintln!("{}: {err}", path.display());
+    }
+}
+
+/// Restores the level saved in `slot` and selects the pack and level it came from,
+/// returns false when the slot could not be loaded.
+fn load_slot(game: &mut Game, slot: usize) -> bool {
+    let path = slot_path(slot);
+    let state = match load_state(&path) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("{}: {err}", path.display());
+            return false;
+        }
+    };
+
+    let pack = game
+        .packs
+        .iter()
+        .position(|pack| pack.path.to_string_lossy() == state.pack_path);
+    match pack {
+        Some(pack) if state.level_index < game.packs[pack].levels.len() => {
+            game.selected_pack = pack;
+            game.selected_level = state.level_index;
+            game.level_issues = validate_level(&game.level_set()[state.level_index]);
+        }
+        _ => eprintln!(
+            "{}: level {} of {} is not loaded, restart will not return to it",
+            path.display(),
+            state.level_index + 1,
+            state.pack_path
+        ),
+    }
+    game.start_override = None;
+    game.cheated = false;
+    game.replay = None;
+    game.current_level = state.level;
+    // saves without players, older ones or made alone, get them like a freshly started level
+    if game.current_level.data.players.is_empty() {
+        add_coop_players(game.coop, &mut game.current_level);
+    }
+    game.rewind.reset(&game.current_level);
+    game.walk = None;
+    true
+}
+
+fn slot_summary(slot: usize) -> String {
+    match load_state(&slot_path(slot)) {
+        Ok(state) => format!(
+            "{}: {} level {} {}, {} infotrons left",
+            slot + 1,
+            state.pack_path,
+            state.level_index + 1,
+            state.level.data.name.trim(),
+            state.level.data.infotrons_required.max(0)
+        ),
+        Err(_) => format!("{}: empty", slot + 1),
+    }
+}
+
+/// While paused ] runs exactly one tick of `REPLAY_DELTA_TIME`, so a move takes eight
+/// presses, and [ takes one back from the rewind buffer. The held keys are the input.
+fn update_frame_step(game: &mut Game) {
+    let level = &mut game.current_level;
+    level.read_keys();
+    let stepping = is_key_pressed(KeyCode::RightBracket);
+    if !stepping && !is_key_pressed(KeyCode::LeftBracket) {
+        return;
+    }
+
+    let before = level.grid.array.clone();
+    if stepping {
+        match &mut game.replay {
+            Some(player) if !player.finished() => player.tick(level),
+            _ => level.tick(REPLAY_DELTA_TIME),
+        }
+        game.rewind.record(level);
+    } else if game.rewind.rewind(level) {
+        if let Some(player) = &mut game.replay {
+            player.step_back();
+        }
+    }
+
+    // timers run down every tick, only tiles that look or act differently count
+    game.step_changes = before
+        .iter()
+        .zip(&level.grid.array)
+        .enumerate()
+        .filter(|(_, (before, after))| !before.same_state(after))
+        .map(|(i, _)| level.grid.coord(i))
+        .collect();
+}
+
+/// Clicking a tile walks Murphy there on a safe path, one move per step in place of the
+/// arrow keys. Pressing an arrow key takes back control.
+fn update_click_move(game: &mut Game) {
+    let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+    if !ctrl && is_mouse_button_pressed(MouseButton::Left) {
+        let world = game.camera.screen_to_world(Vec2::from(mouse_position())) / 16.0;
+        let coord = Coord::new(world.x.floor() as i16, world.y.floor() as i16);
+        game.walk = Walk::new(&game.current_level, coord);
+    }
+    let arrows = [KeyCode::Up, KeyCode::Right, KeyCode::Down, KeyCode::Left];
+    if arrows.into_iter().any(is_key_pressed) {
+        game.walk = None;
+    }
+
+    let Some(walk) = game.walk else {
+        game.current_level.update();
+        return;
+    };
+    let level = &mut game.current_level;
+    level.data.move_priority.clear();
+    match walk.next(level) {
+        WalkStep::Move(mov) => level.data.move_priority.push(mov),
+        WalkStep::Wait => {}
+        WalkStep::Arrived | WalkStep::Unreachable => game.walk = None,
+    }
+    level.tick(get_frame_time() * level.data.speed);
+}
+
+/// Debug controls to start the level from a chosen state: Ctrl+click moves Murphy's start,
+/// I and U (with Shift to decrease) change infotrons and red disks, G toggles gravity and
+/// Backspace stops, restoring the original level.
+fn update_start_override(game: &mut Game) {
+    let level = &game.level_set()[game.selected_level];
+    let mut start = game.start_override.unwrap_or_default();
+    let step = if is_key_down(KeyCode::LeftShift) { -1 } else { 1 };
+    let mut changed = false;
+
+    let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+    if ctrl && is_mouse_button_pressed(MouseButton::Left) {
+        let world = game.camera.screen_to_world(Vec2::from(mouse_position())) / 16.0;
+        let coord = Coord::new(world.x.floor() as i16, world.y.floor() as i16);
+        if level.grid.contains(&coord) {
+            start.murphy = Some(coord);
+            changed = true;
+        }
+    }
+    if is_key_pressed(KeyCode::I) {
+        let infotrons = start.infotrons_required.unwrap_or(level.data.infotrons_required);
+        start.infotrons_required = Some((infotrons + step).max(0));
+        changed = true;
+    }
+    if is_key_pressed(KeyCode::U) {
+        let disks = start.red_utility_disks.unwrap_or(level.data.red_utility_disks);
+        start.red_utility_disks = Some(disks.saturating_add_signed(step));
+        changed = true;
+    }
+    if is_key_pressed(KeyCode::G) {
+        let gravity = start.gravity_enabled.unwrap_or(level.data.gravity_enabled);
+        start.gravity_enabled = Some(!gravity);
+        changed = true;
+    }
+
+    if is_key_pressed(KeyCode::Backspace) {
+        game.start_override = None;
+        restart_level(game);
+    } else if changed {
+        game.start_override = Some(start);
+        restart_level(game);
+    }
 }
 
 fn render(game: &mut Game) {
+    let targets: Vec<Vec2> = game
+        .current_level
+        .data
+        .players
+        .iter()
+        .filter_map(|player| player.camera_target)
+        .collect();
+    let editing = matches!(&game.editor, Some(editor) if !editor.playtesting);
+    if game.split_screen && targets.len() == 2 && !editing {
+        render_split(game, &targets);
+        return;
+    }
+
     if game.freecam.is_none() {
-        if let Some(camera_target) = game.current_level.data.camera_target {
+        // a shared camera keeps the players in the middle
+        if !targets.is_empty() {
+            game.camera.position = targets.iter().sum::<Vec2>() / targets.len() as f32;
+        } else if let Some(camera_target) = game.current_level.data.camera_target {
             game.camera.position = camera_target;
         }
     }
     //game.camera.begin_pp();
     set_camera(&game.camera);
-    game.current_level.draw(game.tiles);
+    match &game.editor {
+        Some(editor) if !editor.playtesting => editor.draw(game.tiles),
+        _ => {
+            game.current_level.draw(game.tiles);
+            if game.danger {
+                overlay::draw_danger(&predict_danger(&game.current_level, DANGER_TICKS));
+            }
+            if game.hints {
+                overlay::draw_route(&hint_route(&game.current_level).unwrap_or_default());
+            }
+            if game.paused {
+                overlay::draw_changes(&game.step_changes);
+            }
+        }
+    }
     //game.camera.end_pp();
     if game.debug_info {
         game.current_level.draw_text();
     }
 }
 
+/// The left half of the screen follows the first player, the right half the second.
+fn render_split(game: &mut Game, targets: &[Vec2]) {
+    let width = screen_width() as i32 & 0x0fff_fffc;
+    let height = screen_height() as i32 & 0x0fff_fffe;
+    for (i, target) in targets.iter().enumerate() {
+        let mut camera = PixelCamera::new(*target, game.camera.zoom, game.camera.speed);
+        camera.viewport = Some((i as i32 * width / 2, 0, width / 2, height));
+        set_camera(&camera);
+        game.current_level.draw(game.tiles);
+        if game.debug_info {
+            game.current_level.draw_text();
+        }
+    }
+    set_default_camera();
+    draw_rectangle(width as f32 / 2.0 - 1.0, 0.0, 2.0, height as f32, DARKGRAY);
+}
+
+fn render_netplay(netplay: &Netplay, text_params: TextParams) {
+    let status = match (netplay.mode, netplay.winner()) {
+        (NetMode::Coop(_), _) => format!("co-op, you are Murphy {}", netplay.seat + 1),
+        (NetMode::Versus, Some(seat)) if seat == netplay.seat => "you won the race".to_string(),
+        (NetMode::Versus, Some(_)) => "the other player won the race".to_string(),
+        (NetMode::Versus, None) => format!(
+            "racing, the other player has {} infotrons to go",
+            netplay.level(1 - netplay.seat).data.infotrons_required.max(0)
+        ),
+    };
+    draw_text_ex(&format!("tick {}: {status}", netplay.tick()), 10.0, 60.0, text_params);
+}
+
 fn render_ui(game: &Game) {
     draw_text_ex(&format!("Fps: {}", get_fps()), 10.0, 60.0, game.text_params);
     draw_text_ex(
@@ -171,4 +1136,85 @@ fn render_ui(game: &Game) {
         140.0,
         game.text_params,
     );
-}
\ No newline at end of file
+    draw_text_ex(
+        &format!("rewind: {} ticks", game.rewind.len()),
+        10.0,
+        160.0,
+        game.text_params,
+    );
+    draw_text_ex(
+        &format!("level issues: {}", game.level_issues.len()),
+        10.0,
+        180.0,
+        game.text_params,
+    );
+    for (i, issue) in game.level_issues.iter().take(8).enumerate() {
+        draw_text_ex(
+            &format!("  {issue}"),
+            10.0,
+            200.0 + i as f32 * 20.0,
+            game.text_params,
+        );
+    }
+    if let Some(start) = &game.start_override {
+        draw_text_ex(
+            &format!("start override: {start:?}"),
+            10.0,
+            380.0,
+            game.text_params,
+        );
+    }
+    if let Some(player) = &game.replay {
+        draw_text_ex(
+            &format!("replay: {} ticks{}", player.total_ticks(), if player.finished() { ", finished" } else { "" }),
+            10.0,
+            400.0,
+            game.text_params,
+        );
+    }
+
+    // the tile under the mouse, in full
+    let world = game.camera.screen_to_world(Vec2::from(mouse_position())) / 16.0;
+    let coord = Coord::new(world.x.floor() as i16, world.y.floor() as i16);
+    if game.current_level.grid.contains(&coord) {
+        draw_text_ex(
+            &format!("tile {},{}: {:?}", coord.x, coord.y, game.current_level.grid.get(&coord)),
+            10.0,
+            420.0,
+            game.text_params,
+        );
+    }
+    if !game.console.open {
+        draw_text_ex("` opens the console", 10.0, 440.0, game.text_params);
+    }
+}
+
+fn render_paused(game: &Game) {
+    draw_text_ex(
+        &format!(
+            "paused, ] steps, [ steps back ({} ticks recorded), F resumes: {} tiles changed",
+            game.rewind.len(),
+            game.step_changes.len()
+        ),
+        10.0,
+        screen_height() - 10.0,
+        game.text_params,
+    );
+}
+
+fn render_pack_menu(game: &Game) {
+    let entries: Vec<String> = game
+        .packs
+        .iter()
+        .map(|pack| {
+            format!(
+                "{} ({:?})  {}/{}",
+                pack.name,
+                pack.format,
+                pack.completed_count(),
+                pack.levels.len()
+            )
+        })
+        .collect();
+    game.pack_menu.draw("Level packs", &entries, game.text_params);
+}
@@ -0,0 +1,52 @@
+use macroquad::{
+    prelude::{is_key_pressed, KeyCode, Color, YELLOW, WHITE},
+    shapes::draw_rectangle,
+    text::{draw_text_ex, TextParams},
+    window::{screen_height, screen_width},
+};
+
+const MENU_BACKGROUND: Color = Color::new(0.0, 0.0, 0.0, 0.8);
+
+/// A list of entries picked with the arrow keys and Enter.
+#[derive(Default)]
+pub struct Menu {
+    pub open: bool,
+    pub selected: usize,
+}
+
+impl Menu {
+    /// Returns the index of the entry activated this frame.
+    pub fn update(&mut self, entry_count: usize) -> Option<usize> {
+        if entry_count == 0 {
+            return None;
+        }
+        self.selected = self.selected.min(entry_count - 1);
+
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = self.selected.checked_sub(1).unwrap_or(entry_count - 1);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.selected = (self.selected + 1) % entry_count;
+        }
+        if is_key_pressed(KeyCode::Enter) {
+            return Some(self.selected);
+        }
+        None
+    }
+
+    pub fn draw(&self, title: &str, entries: &[String], text_params: TextParams) {
+        draw_rectangle(0.0, 0.0, screen_width(), screen_height(), MENU_BACKGROUND);
+
+        let x = screen_width() / 2.0 - 200.0;
+        let mut y = screen_height() / 4.0;
+        draw_text_ex(title, x, y, text_params);
+
+        for (i, entry) in entries.iter().enumerate() {
+            y += 24.0;
+            let mut params = text_params;
+            params.color = if i == self.selected { YELLOW } else { WHITE };
+            let marker = if i == self.selected { "> " } else { "  " };
+            draw_text_ex(&format!("{marker}{entry}"), x, y, params);
+        }
+    }
+}
@@ -0,0 +1,460 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+};
+
+use crate::{
+    json::Json,
+    level::{ExitRule, GameState, Level},
+    replay::{ReplayStep, REPLAY_DELTA_TIME},
+    tile_data::{tile_interaction::TileInteraction, tile_move::TileMove, tile_type::TileType},
+};
+
+/// Inputs take effect this many ticks after they are sent, so a peer can run ahead by that
+/// much before it has to wait for the other.
+pub const NETPLAY_INPUT_DELAY: usize = 4;
+const NETPLAY_VERSION: usize = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NetMode {
+    /// Both players race through their own copy of the level.
+    Versus,
+    /// Both players share one level with two Murphys.
+    Coop(ExitRule),
+}
+
+impl NetMode {
+    const fn name(self) -> &'static str {
+        match self {
+            NetMode::Versus => "versus",
+            NetMode::Coop(ExitRule::Any) => "coop-any",
+            NetMode::Coop(ExitRule::All) => "coop-all",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    Disconnected,
+    Protocol(String),
+    /// The peers computed different levels, the simulation is no longer the same on both.
+    Desync { tick: usize, local: u64, remote: u64 },
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(err) => write!(f, "{err}"),
+            NetError::Disconnected => write!(f, "the other player disconnected"),
+            NetError::Protocol(msg) => write!(f, "{msg}"),
+            NetError::Desync {
+                tick,
+                local,
+                remote,
+            } => write!(f, "desync at tick {tick}: {local:016x} here, {remote:016x} there"),
+        }
+    }
+}
+
+impl From<io::Error> for NetError {
+    fn from(err: io::Error) -> Self {
+        NetError::Io(err)
+    }
+}
+
+/// Two players running the same levels in lockstep. Every tick both send their input and a
+/// hash of what they simulated, and no tick runs before the input of both players is known,
+/// so the levels stay identical as long as `Level::tick` is deterministic.
+pub struct Netplay {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    pub mode: NetMode,
+    /// 0 for the host, 1 for the player who joined.
+    pub seat: usize,
+    /// One level per seat in versus, a single level with a player per Murphy in co-op.
+    pub levels: Vec<Level>,
+    tick: usize,
+    received: usize,
+    local_inputs: VecDeque<ReplayStep>,
+    remote_inputs: VecDeque<ReplayStep>,
+    local_hashes: VecDeque<u64>,
+    remote_hashes: VecDeque<u64>,
+    checked: usize,
+}
+
+impl Netplay {
+    /// Waits on `address` for the other player to join, who must have loaded the same level.
+    pub fn host(address: &str, level: &Level, mode: NetMode) -> Result<Self, NetError> {
+        let levels = start_levels(level, mode)?;
+        let (stream, _) = TcpListener::bind(address)?.accept()?;
+        let mut netplay = Self::new(stream, mode, 0, levels)?;
+
+        let hello = Json::object([
+            ("netplay", NETPLAY_VERSION.into()),
+            ("mode", mode.name().into()),
+            ("level", hash_string(level_hash(level)).into()),
+        ]);
+        netplay.send(&hello)?;
+        let reply = netplay.receive_json()?;
+        check_hello(&reply, level)?;
+        Ok(netplay)
+    }
+
+    /// Connects to a host on `address`, the mode is the one the host chose.
+    pub fn join(address: &str, level: &Level) -> Result<Self, NetError> {
+        let stream = TcpStream::connect(address)?;
+        let mut netplay = Self::new(stream, NetMode::Versus, 1, Vec::new())?;
+
+        let hello = netplay.receive_json()?;
+        check_hello(&hello, level)?;
+        netplay.mode = match hello.get("mode").and_then(Json::as_str) {
+            Some("versus") => NetMode::Versus,
+            Some("coop-any") => NetMode::Coop(ExitRule::Any),
+            Some("coop-all") => NetMode::Coop(ExitRule::All),
+            _ => return Err(NetError::Protocol("the host sent an unknown mode".to_string())),
+        };
+        netplay.send(&Json::object([
+            ("netplay", NETPLAY_VERSION.into()),
+            ("level", hash_string(level_hash(level)).into()),
+        ]))?;
+        netplay.levels = start_levels(level, netplay.mode)?;
+        Ok(netplay)
+    }
+
+    fn new(stream: TcpStream, mode: NetMode, seat: usize, levels: Vec<Level>) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let idle = vec![ReplayStep::new(TileMove::None); NETPLAY_INPUT_DELAY];
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            mode,
+            seat,
+            levels,
+            tick: 0,
+            received: 0,
+            local_inputs: idle.clone().into(),
+            remote_inputs: idle.into(),
+            local_hashes: VecDeque::new(),
+            remote_hashes: VecDeque::new(),
+            checked: 0,
+        })
+    }
+
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+
+    /// The level `seat` plays, both seats play the same one in co-op.
+    pub fn level(&self, seat: usize) -> &Level {
+        &self.levels[seat.min(self.levels.len() - 1)]
+    }
+
+    /// Sends `input` for a later tick, then runs one tick once the other player's input for
+    /// it has arrived.
+    pub fn step(&mut self, input: ReplayStep) -> Result<(), NetError> {
+        let hash = self.levels.iter().fold(FNV_OFFSET, |hash, level| {
+            fnv(hash, &level_hash(level).to_le_bytes())
+        });
+        self.local_hashes.push_back(hash);
+        self.local_inputs.push_back(input);
+        self.send(&Json::object([
+            ("tick", self.tick.into()),
+            ("input", input.to_char().to_string().into()),
+            ("hash", hash_string(hash).into()),
+        ]))?;
+
+        while self.remote_inputs.is_empty() {
+            self.receive_input()?;
+        }
+        self.check_hashes()?;
+
+        let local = self.local_inputs.pop_front().unwrap();
+        let remote = self.remote_inputs.pop_front().unwrap();
+        let inputs = if self.seat == 0 {
+            [local, remote]
+        } else {
+            [remote, local]
+        };
+        match self.mode {
+            NetMode::Versus => {
+                for (level, input) in self.levels.iter_mut().zip(inputs) {
+                    input.apply(level);
+                }
+            }
+            NetMode::Coop(_) => {
+                let players = &mut self.levels[0].data.players;
+                for (player, input) in players.iter_mut().zip(inputs) {
+                    input.apply_to_player(player);
+                }
+            }
+        }
+        for level in &mut self.levels {
+            level.tick(REPLAY_DELTA_TIME);
+        }
+        self.tick += 1;
+        Ok(())
+    }
+
+    /// Co-op ends like a normal level, versus once someone finished or everyone died.
+    pub fn game_over(&self) -> bool {
+        let states = || self.levels.iter().map(|level| level.data.game_state);
+        states().any(|state| state == GameState::Finished)
+            || states().all(|state| state == GameState::Died)
+    }
+
+    /// The seat that finished first in versus, `None` for a draw or while still racing.
+    pub fn winner(&self) -> Option<usize> {
+        let finished: Vec<usize> = (0..self.levels.len())
+            .filter(|&seat| self.levels[seat].data.game_state == GameState::Finished)
+            .collect();
+        match (self.mode, finished.as_slice()) {
+            (NetMode::Versus, [seat]) => Some(*seat),
+            _ => None,
+        }
+    }
+
+    /// Waits until the other player has stopped too, closing right away could throw away
+    /// inputs they have not read yet.
+    pub fn close(mut self) -> io::Result<()> {
+        self.writer.shutdown(Shutdown::Write)?;
+        io::copy(&mut self.reader, &mut io::sink())?;
+        Ok(())
+    }
+
+    fn send(&mut self, message: &Json) -> io::Result<()> {
+        writeln!(self.writer, "{message}")
+    }
+
+    fn receive_json(&mut self) -> Result<Json, NetError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(NetError::Disconnected);
+        }
+        Json::parse(&line).map_err(NetError::Protocol)
+    }
+
+    fn receive_input(&mut self) -> Result<(), NetError> {
+        let message = self.receive_json()?;
+        let bad = || NetError::Protocol(format!("bad message: {message}"));
+
+        if message.get("tick").and_then(Json::as_usize) != Some(self.received) {
+            return Err(bad());
+        }
+        let input = message
+            .get("input")
+            .and_then(Json::as_str)
+            .and_then(|input| input.chars().next())
+            .and_then(ReplayStep::from_char)
+            .ok_or_else(bad)?;
+        let hash = message
+            .get("hash")
+            .and_then(Json::as_str)
+            .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+            .ok_or_else(bad)?;
+
+        self.remote_inputs.push_back(input);
+        self.remote_hashes.push_back(hash);
+        self.received += 1;
+        Ok(())
+    }
+
+    /// Compares the hashes of every tick both players have simulated.
+    fn check_hashes(&mut self) -> Result<(), NetError> {
+        while let (Some(&local), Some(&remote)) =
+            (self.local_hashes.front(), self.remote_hashes.front())
+        {
+            if local != remote {
+                return Err(NetError::Desync {
+                    tick: self.checked,
+                    local,
+                    remote,
+                });
+            }
+            self.local_hashes.pop_front();
+            self.remote_hashes.pop_front();
+            self.checked += 1;
+        }
+        Ok(())
+    }
+}
+
+fn start_levels(level: &Level, mode: NetMode) -> Result<Vec<Level>, NetError> {
+    match mode {
+        NetMode::Versus => Ok(vec![level.clone(), level.clone()]),
+        NetMode::Coop(exit_rule) => {
+            let mut level = level.clone();
+            match level.add_players(exit_rule) {
+                2 => Ok(vec![level]),
+                count => Err(NetError::Protocol(format!(
+                    "co-op needs a level with two Murphys, this one has {count}"
+                ))),
+            }
+        }
+    }
+}
+
+fn check_hello(hello: &Json, level: &Level) -> Result<(), NetError> {
+    if hello.get("netplay").and_then(Json::as_usize) != Some(NETPLAY_VERSION) {
+        return Err(NetError::Protocol("the other player runs another version".to_string()));
+    }
+    if hello.get("level").and_then(Json::as_str) != Some(&hash_string(level_hash(level))) {
+        return Err(NetError::Protocol("the other player loaded another level".to_string()));
+    }
+    Ok(())
+}
+
+fn hash_string(hash: u64) -> String {
+    format!("{hash:016x}")
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// FNV-1a over everything a tick can change, the same on every machine and build, unlike
+/// the std hasher.
+pub fn level_hash(level: &Level) -> u64 {
+    let data = &level.data;
+    let mut hash = fnv(FNV_OFFSET, &data.infotrons_required.to_le_bytes());
+    hash = fnv(hash, &data.red_utility_disks.to_le_bytes());
+    hash = fnv(
+        hash,
+        &[
+            data.game_state as u8,
+            data.gravity_enabled as u8,
+            data.zonks_frozen as u8,
+            data.exit_rule as u8,
+        ],
+    );
+    for tile in &level.grid.array {
+        hash = fnv(
+            hash,
+            &[
+                tile.typ.index(),
+                tile.state as u8,
+                tile.mov as u8,
+                tile.mov2 as u8,
+            ],
+        );
+        let (interaction, typ) = match tile.int {
+            TileInteraction::None => (0, TileType::None),
+            TileInteraction::Eating(typ) => (1, typ),
+            TileInteraction::Pushing => (2, TileType::None),
+            TileInteraction::Slurping => (3, TileType::None),
+            TileInteraction::Tunneling(typ) => (4, typ),
+            TileInteraction::Moving => (5, TileType::None),
+            TileInteraction::Rotating => (6, TileType::None),
+        };
+        hash = fnv(hash, &[interaction, typ.index()]);
+        hash = fnv(hash, &tile.upd.to_bits().to_le_bytes());
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread, time::Duration};
+
+    use super::*;
+    use crate::{grid::Coord, level_loader::parse_level_text};
+
+    const LEVEL: &str = "\
+########
+#M.O.*E#
+#..O  .#
+#*.  . #
+########";
+
+    fn free_address() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    /// Joins once the host thread is listening.
+    fn join(address: &str, level: &Level) -> Result<Netplay, NetError> {
+        for _ in 0..100 {
+            match Netplay::join(address, level) {
+                Err(NetError::Io(err)) if err.kind() == io::ErrorKind::ConnectionRefused => {
+                    thread::sleep(Duration::from_millis(10))
+                }
+                result => return result,
+            }
+        }
+        panic!("nobody is hosting on {address}");
+    }
+
+    #[test]
+    fn peers_stay_in_sync() {
+        let level = parse_level_text(LEVEL).unwrap();
+        let address = free_address();
+
+        let host_level = level.clone();
+        let host_address = address.clone();
+        let host = thread::spawn(move || {
+            let mut netplay = Netplay::host(&host_address, &host_level, NetMode::Versus).unwrap();
+            for mov in [TileMove::Right, TileMove::Down, TileMove::Right, TileMove::None] {
+                for _ in 0..16 {
+                    netplay.step(ReplayStep::new(mov)).unwrap();
+                }
+            }
+            let hashes: Vec<u64> = netplay.levels.iter().map(level_hash).collect();
+            netplay.close().unwrap();
+            hashes
+        });
+
+        let mut netplay = join(&address, &level).unwrap();
+        for mov in [TileMove::Down, TileMove::None, TileMove::Right, TileMove::Up] {
+            for _ in 0..16 {
+                netplay.step(ReplayStep::new(mov)).unwrap();
+            }
+        }
+        let hashes: Vec<u64> = netplay.levels.iter().map(level_hash).collect();
+        netplay.close().unwrap();
+
+        assert_eq!(host.join().unwrap(), hashes);
+        assert_ne!(hashes[0], hashes[1], "the seats played different inputs");
+    }
+
+    #[test]
+    fn changed_level_is_a_desync() {
+        let level = parse_level_text(LEVEL).unwrap();
+        let address = free_address();
+
+        let host_level = level.clone();
+        let host_address = address.clone();
+        let host = thread::spawn(move || {
+            let mut netplay = Netplay::host(&host_address, &host_level, NetMode::Versus).unwrap();
+            netplay.levels[0].grid.set(&Coord::new(2, 1), TileType::Empty.to_tile());
+            // the other player's hashes arrive after the input delay
+            for _ in 0..=NETPLAY_INPUT_DELAY {
+                netplay.step(ReplayStep::new(TileMove::None))?;
+            }
+            Ok(())
+        });
+
+        let mut netplay = join(&address, &level).unwrap();
+        for _ in 0..=NETPLAY_INPUT_DELAY {
+            if netplay.step(ReplayStep::new(TileMove::None)).is_err() {
+                break;
+            }
+        }
+
+        match host.join().unwrap() {
+            Err(NetError::Desync { tick, local, remote }) => {
+                assert_eq!(tick, 0);
+                assert_ne!(local, remote);
+            }
+            other => panic!("expected a desync, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use macroquad::{
+    prelude::{Color, GREEN, ORANGE, RED, YELLOW},
+    shapes::{draw_rectangle, draw_rectangle_lines},
+};
+
+use supaplex::{grid::Coord, pathfinding::RouteStep};
+
+/// Shades the tiles of a hint route, risky steps in red.
+pub fn draw_route(route: &[RouteStep]) {
+    for step in route {
+        let color = if step.risky { RED } else { GREEN };
+        shade_tile(step.coord.x, step.coord.y, Color { a: 0.35, ..color });
+    }
+}
+
+/// Shades the tiles `predict_danger` expects to be hit.
+pub fn draw_danger(tiles: &[Coord]) {
+    for coord in tiles {
+        shade_tile(coord.x, coord.y, Color { a: 0.4, ..ORANGE });
+    }
+}
+
+/// Outlines the tiles that changed in the last frame step.
+pub fn draw_changes(tiles: &[Coord]) {
+    for coord in tiles {
+        draw_rectangle_lines(coord.x as f32 * 16.0, coord.y as f32 * 16.0, 16.0, 16.0, 1.0, YELLOW);
+    }
+}
+
+fn shade_tile(x: i16, y: i16, color: Color) {
+    draw_rectangle(x as f32 * 16.0, y as f32 * 16.0, 16.0, 16.0, color);
+}
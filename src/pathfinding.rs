@@ -0,0 +1,286 @@
+use std::collections::VecDeque;
+
+use crate::{
+    grid::{Coord, Grid},
+    level::Level,
+    tile_data::{
+        systems::gravity_system::roll_direction, tile::Tile, tile_move::TileMove,
+        tile_state::TileState, tile_type::TileType,
+    },
+};
+
+/// One step of Murphy on a grid that stands still: where he ends up moving `mov` from `from`,
+/// `None` when he cannot go there. He walks onto anything he can eat, passes ports he fits
+/// through and stops at the exit, he never pushes anything and never steps onto danger.
+/// The grid does not say whether the exit is open yet, `Walk` closes it while infotrons
+/// are still required.
+pub fn murphy_step(grid: &Grid, from: &Coord, mov: TileMove) -> Option<Coord> {
+    if grid.get(from).typ == TileType::Exit {
+        return None;
+    }
+
+    let to = from.offset(&mov);
+    let tile = grid.get(&to);
+    match tile.state {
+        TileState::Eatable => Some(to),
+        TileState::Tunnelable => {
+            let behind = to.offset(&mov);
+            (tile.typ.port_allows(mov) && grid.get(&behind).typ == TileType::Empty)
+                .then_some(behind)
+        }
+        _ => (tile.typ == TileType::Exit).then_some(to),
+    }
+}
+
+/// Like `murphy_step`, but never onto a tile next to something dangerous: a falling zonk or
+/// infotron, an explosion or an enemy.
+pub fn safe_step(grid: &Grid, from: &Coord, mov: TileMove) -> Option<Coord> {
+    let to = murphy_step(grid, from, mov)?;
+    let danger = grid
+        .neighbours4(&to)
+        .any(|n| grid.get(&n).state == TileState::Dangerous);
+    (!danger).then_some(to)
+}
+
+/// What Murphy should do next on his way to a tile, see `Walk::next`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WalkStep {
+    Move(TileMove),
+    /// Every way there is dangerous right now, but may clear up.
+    Wait,
+    Arrived,
+    /// Murphy is gone or there is no way there at all.
+    Unreachable,
+}
+
+/// Walks Murphy to `goal` one move at a time. The path is planned again for every move, so
+/// Murphy goes around whatever moved into the way since.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Walk {
+    pub goal: Coord,
+}
+
+impl Walk {
+    /// `None` when Murphy cannot get to `goal` even with nothing dangerous around.
+    pub fn new(level: &Level, goal: Coord) -> Option<Self> {
+        let grid = &level.grid;
+        let murphy = grid.find(TileType::Murphy)?;
+        grid.shortest_path(murphy, |coord, _| *coord == goal, exit_rule(level, murphy_step))?;
+        Some(Self { goal })
+    }
+
+    pub fn next(&self, level: &Level) -> WalkStep {
+        let grid = &level.grid;
+        let Some(murphy) = grid.find(TileType::Murphy) else {
+            return WalkStep::Unreachable;
+        };
+        if murphy == self.goal {
+            return WalkStep::Arrived;
+        }
+        let goal = self.goal;
+        let is_goal = |coord: &Coord, _: &Tile| *coord == goal;
+        if let Some(path) = grid.shortest_path(murphy, is_goal, exit_rule(level, safe_step)) {
+            return WalkStep::Move(path[0].0);
+        }
+        match grid.shortest_path(murphy, is_goal, exit_rule(level, murphy_step)) {
+            Some(_) => WalkStep::Wait,
+            None => WalkStep::Unreachable,
+        }
+    }
+}
+
+/// `step` in `level`, where Murphy cannot walk into the exit while infotrons are still
+/// required.
+fn exit_rule<'a>(
+    level: &'a Level,
+    step: impl Fn(&Grid, &Coord, TileMove) -> Option<Coord> + 'a,
+) -> impl Fn(&Grid, &Coord, TileMove) -> Option<Coord> + 'a {
+    let open = level.data.infotrons_required <= 0;
+    move |grid, from, mov| {
+        step(grid, from, mov).filter(|to| open || grid.get(to).typ != TileType::Exit)
+    }
+}
+
+impl Grid {
+    /// Every tile reachable from `start` by repeating `step` in the four directions, `start`
+    /// first and the rest in the order a breadth-first search finds them.
+    pub fn flood_fill(
+        &self,
+        start: Coord,
+        step: impl Fn(&Grid, &Coord, TileMove) -> Option<Coord>,
+    ) -> Vec<Coord> {
+        let Some(start_index) = self.index(&start) else {
+            return Vec::new();
+        };
+        let mut visited = vec![false; self.array.len()];
+        visited[start_index] = true;
+
+        let mut found = vec![start];
+        let mut next = 0;
+        while let Some(&coord) = found.get(next) {
+            next += 1;
+            for mov in TileMove::DIRECTIONS {
+                let Some(to) = step(self, &coord, mov) else {
+                    continue;
+                };
+                if let Some(i) = self.index(&to).filter(|&i| !visited[i]) {
+                    visited[i] = true;
+                    found.push(to);
+                }
+            }
+        }
+        found
+    }
+
+    /// The tiles Murphy can walk to from `start` as the grid is now, see `murphy_step`.
+    pub fn reachable(&self, start: Coord) -> Vec<Coord> {
+        self.flood_fill(start, murphy_step)
+    }
+
+    /// The fewest steps from `start` to the nearest tile `is_goal` accepts, as the move of
+    /// every step and the tile it ends on. Of several shortest ways the one turning clockwise
+    /// from up first wins, so the result is always the same.
+    pub fn shortest_path(
+        &self,
+        start: Coord,
+        is_goal: impl Fn(&Coord, &Tile) -> bool,
+        step: impl Fn(&Grid, &Coord, TileMove) -> Option<Coord>,
+    ) -> Option<Vec<(TileMove, Coord)>> {
+        let start_index = self.index(&start)?;
+        // how each tile was first reached: the tile before it and the move from there
+        let mut came_from: Vec<Option<(usize, TileMove)>> = vec![None; self.array.len()];
+        let mut visited = vec![false; self.array.len()];
+        visited[start_index] = true;
+
+        let mut queue = VecDeque::from([start_index]);
+        while let Some(i) = queue.pop_front() {
+            let coord = self.coord(i);
+            if is_goal(&coord, &self.array[i]) {
+                let mut path = Vec::new();
+                let mut at = i;
+                while let Some((from, mov)) = came_from[at] {
+                    path.push((mov, self.coord(at)));
+                    at = from;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for mov in TileMove::DIRECTIONS {
+                let Some(to) = step(self, &coord, mov) else {
+                    continue;
+                };
+                if let Some(j) = self.index(&to).filter(|&j| !visited[j]) {
+                    visited[j] = true;
+                    came_from[j] = Some((i, mov));
+                    queue.push_back(j);
+                }
+            }
+        }
+        None
+    }
+
+    /// Murphy's shortest walk from `start` to `goal`, see `murphy_step`.
+    pub fn murphy_path(&self, start: Coord, goal: Coord) -> Option<Vec<(TileMove, Coord)>> {
+        self.shortest_path(start, |coord, _| *coord == goal, murphy_step)
+    }
+}
+
+/// One step of a hint route, `risky` when a zonk or infotron falls onto the tile once Murphy
+/// has eaten his way there, from right above or rolling off a round tile next to that.
+/// Zonks fall even in levels with `zonks_frozen` set, the simulation does not read it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RouteStep {
+    pub mov: TileMove,
+    pub coord: Coord,
+    pub risky: bool,
+}
+
+/// Whether a zonk or infotron falls into the empty tile at `coord`, straight down or by
+/// rolling into the tile above it off a round tile beside that, as `gravity_system` would.
+fn falls_onto(grid: &Grid, coord: &Coord) -> bool {
+    let falls = |coord: &Coord| matches!(grid.get(coord).typ, TileType::Zonk | TileType::Infotron);
+    let above = coord.offset(&TileMove::Up);
+    if falls(&above) {
+        return true;
+    }
+    grid.get(&above).typ == TileType::Empty
+        && [TileMove::Left, TileMove::Right].into_iter().any(|side| {
+            let from = above.offset(&side);
+            falls(&from) && roll_direction(grid, &from) == Some(side.opposite())
+        })
+}
+
+/// Murphy's shortest walk to the nearest infotron, or to the exit once no more infotrons
+/// are required, as the level is now. `None` when there is no way to either.
+pub fn hint_route(level: &Level) -> Option<Vec<RouteStep>> {
+    let grid = &level.grid;
+    let murphy = grid.find(TileType::Murphy)?;
+    let goal = if level.data.infotrons_required <= 0 {
+        TileType::Exit
+    } else {
+        TileType::Infotron
+    };
+    let path = grid.shortest_path(murphy, |_, tile| tile.typ == goal, murphy_step)?;
+
+    // the tiles Murphy has eaten or left by each step are empty from then on
+    let mut eaten = grid.clone();
+    eaten.set(&murphy, TileType::Empty.to_tile());
+    Some(
+        path.into_iter()
+            .map(|(mov, coord)| {
+                eaten.set(&coord, TileType::Empty.to_tile());
+                RouteStep {
+                    mov,
+                    coord,
+                    risky: falls_onto(&eaten, &coord),
+                }
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_loader::parse_level_text;
+
+    #[test]
+    fn the_exit_is_closed_while_infotrons_are_required() {
+        let mut level = parse_level_text("infotrons: 1\n######\n#M.E*#\n######").unwrap();
+        let exit = Coord::new(3, 1);
+        assert_eq!(Walk::new(&level, exit), None);
+
+        level.data.infotrons_required = 0;
+        let walk = Walk::new(&level, exit).unwrap();
+        assert_eq!(walk.next(&level), WalkStep::Move(TileMove::Right));
+    }
+
+    #[test]
+    fn a_zonk_rolling_off_a_chip_makes_a_step_risky() {
+        let level = parse_level_text("infotrons: 1\n######\n#O   #\n#R*.M#\n######").unwrap();
+        let route = hint_route(&level).unwrap();
+        let risky: Vec<bool> = route.iter().map(|step| step.risky).collect();
+        assert_eq!(risky, [false, true]);
+    }
+
+    #[test]
+    fn murphy_reaches_what_he_can_eat_and_pass() {
+        let grid = parse_level_text("#######\n#M.+ O#\n#.###E#\n#######").unwrap().grid;
+        let mut reachable = grid.reachable(Coord::new(1, 1));
+        reachable.sort_by_key(|c| (c.y, c.x));
+        // through the port onto the empty tile behind it, but not past the zonk
+        let expected = [(1, 1), (2, 1), (4, 1), (1, 2)].map(|(x, y)| Coord::new(x, y));
+        assert_eq!(reachable, expected);
+    }
+
+    #[test]
+    fn the_shortest_path_goes_around_walls() {
+        let grid = parse_level_text("######\n#M#E.#\n#....#\n######").unwrap().grid;
+        let path = grid.murphy_path(Coord::new(1, 1), Coord::new(3, 1)).unwrap();
+        let moves: Vec<TileMove> = path.iter().map(|(mov, _)| *mov).collect();
+        assert_eq!(moves, [TileMove::Down, TileMove::Right, TileMove::Right, TileMove::Up]);
+        assert_eq!(path.last().unwrap().1, Coord::new(3, 1));
+        assert_eq!(grid.murphy_path(Coord::new(1, 1), Coord::new(0, 0)), None);
+    }
+}
@@ -9,11 +9,13 @@ pub struct PixelCamera {
     pub position: Vec2,
     pub zoom: f32,
     pub speed: f32,
+    /// Part of the screen drawn to as x, y, width and height, the whole screen when `None`.
+    pub viewport: Option<(i32, i32, i32, i32)>,
 }
 
 impl PixelCamera {
     pub fn new(position: Vec2, zoom: f32, speed: f32) -> Self {
-        Self { position, zoom, speed }
+        Self { position, zoom, speed, viewport: None }
     }
 
     pub fn move_camera_with_keys(
@@ -43,6 +45,13 @@ impl PixelCamera {
         }
     }
 
+    pub fn screen_to_world(&self, screen: Vec2) -> Vec2 {
+        let even_width = (screen_width() as i32 & 0x0fff_fffe) as f32;
+        let even_height = (screen_height() as i32 & 0x0fff_fffe) as f32;
+
+        self.position + (screen - vec2(even_width, even_height) / 2.0) / self.zoom
+    }
+
     pub fn begin_pp(&mut self) {
         unsafe {
             miniquad::gl::glDisable(GL_MULTISAMPLE);
@@ -58,8 +67,9 @@ impl PixelCamera {
 
 impl Camera for PixelCamera {
     fn matrix(&self) -> macroquad::prelude::Mat4 {
-        let even_width = (screen_width() as i32 & 0x0fff_fffe) as f32;
-        let even_height = (screen_height() as i32 & 0x0fff_fffe) as f32;
+        let (_, _, width, height) = self.viewport().unwrap();
+        let even_width = width as f32;
+        let even_height = height as f32;
 
         let matw = 2.0 / even_width * self.zoom;
         let math = -2.0 / even_height * self.zoom;
@@ -84,6 +94,9 @@ impl Camera for PixelCamera {
     }
 
     fn viewport(&self) -> Option<(i32, i32, i32, i32)> {
+        if self.viewport.is_some() {
+            return self.viewport;
+        }
         let even_width = screen_width() as i32 & 0x0fff_fffe;
         let even_height = screen_height() as i32 & 0x0fff_fffe;
 
@@ -0,0 +1,120 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+};
+
+use macroquad::prelude::{Image, BLACK};
+
+use crate::{
+    grid::{Coord, FCoord},
+    level::Level,
+    tile_data::sprite::{SpriteTarget, SPRITE_SIZE, SPRITE_STRIDE},
+};
+
+const PNG_MAGIC: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// An image in memory that sprites are copied onto pixel by pixel, so levels can be rendered
+/// without a window or GPU.
+pub struct SpriteCanvas<'a> {
+    sheet: &'a Image,
+    pub image: Image,
+}
+
+impl<'a> SpriteCanvas<'a> {
+    /// A black canvas of `width` by `height` tiles.
+    pub fn new(sheet: &'a Image, width: i16, height: i16) -> io::Result<Self> {
+        let size = |tiles: i16| {
+            u16::try_from(tiles as u32 * SPRITE_SIZE)
+                .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "level too large for an image"))
+        };
+        let image = Image::gen_image_color(size(width)?, size(height)?, BLACK);
+        Ok(Self { sheet, image })
+    }
+}
+
+impl SpriteTarget for SpriteCanvas<'_> {
+    fn draw_sprite(&mut self, dst: &FCoord, src: &Coord, flip_x: bool, flip_y: bool) {
+        let dst_x = (dst.x * SPRITE_SIZE as f32).round() as i32;
+        let dst_y = (dst.y * SPRITE_SIZE as f32).round() as i32;
+        let src_x = src.x as i32 * SPRITE_STRIDE as i32;
+        let src_y = src.y as i32 * SPRITE_STRIDE as i32;
+        let size = SPRITE_SIZE as i32;
+
+        for y in 0..size {
+            for x in 0..size {
+                let (to_x, to_y) = (dst_x + x, dst_y + y);
+                if to_x < 0
+                    || to_y < 0
+                    || to_x >= self.image.width as i32
+                    || to_y >= self.image.height as i32
+                {
+                    continue;
+                }
+                let from_x = src_x + if flip_x { size - 1 - x } else { x };
+                let from_y = src_y + if flip_y { size - 1 - y } else { y };
+                if from_x >= self.sheet.width as i32 || from_y >= self.sheet.height as i32 {
+                    continue;
+                }
+
+                let from = pixel_index(self.sheet, from_x, from_y);
+                let to = pixel_index(&self.image, to_x, to_y);
+                let alpha = self.sheet.bytes[from + 3] as u32;
+                for channel in 0..3 {
+                    let src = self.sheet.bytes[from + channel] as u32;
+                    let dst = &mut self.image.bytes[to + channel];
+                    *dst = ((src * alpha + *dst as u32 * (255 - alpha)) / 255) as u8;
+                }
+                self.image.bytes[to + 3] = 255;
+            }
+        }
+    }
+}
+
+fn pixel_index(image: &Image, x: i32, y: i32) -> usize {
+    (y as usize * image.width as usize + x as usize) * 4
+}
+
+pub fn load_sprite_sheet(path: &str) -> io::Result<Image> {
+    let bytes = fs::read(path)?;
+    if !bytes.starts_with(PNG_MAGIC) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{path}: not a PNG file"),
+        ));
+    }
+    // `Image::from_file_with_format` panics on a broken file
+    let image = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, format!("{path}: {err}")))?
+        .to_rgba8();
+    Ok(Image {
+        width: image.width() as u16,
+        height: image.height() as u16,
+        bytes: image.into_raw(),
+    })
+}
+
+/// Draws `level` the same way the game does, through `Tile::draw`.
+pub fn render_level(level: &Level, sheet: &Image) -> io::Result<Image> {
+    let mut canvas = SpriteCanvas::new(sheet, level.grid.width, level.grid.height)?;
+    level.draw(&mut canvas);
+    Ok(canvas.image)
+}
+
+pub fn save_png(path: &str, image: &Image) -> io::Result<()> {
+    // fail with an error instead of the panic inside `export_png`
+    fs::File::create(path)?;
+
+    // `export_png` flips the rows, since it expects images read back from the GPU
+    let row = image.width as usize * 4;
+    let mut flipped = image.clone();
+    for (to, from) in flipped
+        .bytes
+        .chunks_exact_mut(row)
+        .zip(image.bytes.chunks_exact(row).rev())
+    {
+        to.copy_from_slice(from);
+    }
+    flipped.export_png(path);
+
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use std::{fmt, fs, io};
+
+use crate::{
+    level::{Level, Player},
+    level_loader::invalid_data,
+    tile_data::tile_move::TileMove,
+};
+
+pub const REPLAY_DELTA_TIME: f32 = 0.125;
+pub const REPLAY_TICKS_PER_STEP: usize = 8;
+
+/// One held input, `slurping` is Space held together with the direction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ReplayStep {
+    pub mov: TileMove,
+    pub slurping: bool,
+}
+
+impl ReplayStep {
+    pub const fn new(mov: TileMove) -> Self {
+        Self {
+            mov,
+            slurping: false,
+        }
+    }
+
+    /// `mov` with Space held, Murphy then eats the tile next to him without moving.
+    pub const fn slurp(mov: TileMove) -> Self {
+        Self {
+            mov,
+            slurping: true,
+        }
+    }
+
+    pub const fn to_char(self) -> char {
+        let c = match self.mov {
+            TileMove::Up => 'U',
+            TileMove::Right => 'R',
+            TileMove::Down => 'D',
+            TileMove::Left => 'L',
+            TileMove::None => return '.',
+        };
+        if self.slurping {
+            c.to_ascii_lowercase()
+        } else {
+            c
+        }
+    }
+
+    pub const fn from_char(c: char) -> Option<Self> {
+        let mov = match c.to_ascii_uppercase() {
+            'U' => TileMove::Up,
+            'R' => TileMove::Right,
+            'D' => TileMove::Down,
+            'L' => TileMove::Left,
+            '.' => TileMove::None,
+            _ => return None,
+        };
+        Some(Self {
+            mov,
+            slurping: c.is_ascii_lowercase(),
+        })
+    }
+
+    /// Makes this the input `level` sees on its next ticks.
+    pub fn apply(self, level: &mut Level) {
+        level.data.move_priority.clear();
+        if self.mov != TileMove::None {
+            level.data.move_priority.push(self.mov);
+        }
+        level.data.slurping = self.slurping;
+    }
+
+    /// Makes this the input of one player of a level with several Murphys.
+    pub fn apply_to_player(self, player: &mut Player) {
+        player.move_priority.clear();
+        if self.mov != TileMove::None {
+            player.move_priority.push(self.mov);
+        }
+        player.slurping = self.slurping;
+    }
+
+    /// Holds this input on `level` for `ticks` fixed ticks.
+    pub fn play(self, level: &mut Level, delta_time: f32, ticks: usize) {
+        self.apply(level);
+        for _ in 0..ticks {
+            level.tick(delta_time);
+        }
+    }
+}
+
+/// A sequence of inputs, each held for `ticks_per_step` ticks of `delta_time`.
+///
+/// Stored as text:
+/// ```text
+/// level 1
+/// delta_time 0.125
+/// ticks_per_step 8
+/// steps RRDD.Lu
+/// ```
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub level: usize,
+    pub delta_time: f32,
+    pub ticks_per_step: usize,
+    pub steps: Vec<ReplayStep>,
+}
+
+impl Replay {
+    pub fn new(level: usize, steps: Vec<ReplayStep>) -> Self {
+        Self {
+            level,
+            delta_time: REPLAY_DELTA_TIME,
+            ticks_per_step: REPLAY_TICKS_PER_STEP,
+            steps,
+        }
+    }
+
+    pub fn play(&self, level: &mut Level) {
+        for step in &self.steps {
+            step.play(level, self.delta_time, self.ticks_per_step);
+        }
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut replay = Self::new(0, Vec::new());
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            match key {
+                "level" => {
+                    let level: usize = value.parse().map_err(|_| invalid_data(format!("bad level: {value}")))?;
+                    replay.level = level.saturating_sub(1);
+                }
+                "delta_time" => {
+                    replay.delta_time = value
+                        .parse()
+                        .map_err(|_| invalid_data(format!("bad delta_time: {value}")))?;
+                }
+                "ticks_per_step" => {
+                    replay.ticks_per_step = value
+                        .parse()
+                        .map_err(|_| invalid_data(format!("bad ticks_per_step: {value}")))?;
+                }
+                "steps" => {
+                    for c in value.chars() {
+                        let step = ReplayStep::from_char(c)
+                            .ok_or_else(|| invalid_data(format!("bad step: {c}")))?;
+                        replay.steps.push(step);
+                    }
+                }
+                _ => return Err(invalid_data(format!("unknown key: {key}"))),
+            }
+        }
+
+        Ok(replay)
+    }
+}
+
+/// Plays a replay one tick at a time, so it can be watched.
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    tick: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, tick: 0 }
+    }
+
+    pub fn total_ticks(&self) -> usize {
+        self.replay.steps.len() * self.replay.ticks_per_step
+    }
+
+    pub fn finished(&self) -> bool {
+        self.tick >= self.total_ticks()
+    }
+
+    pub fn restart(&mut self) {
+        self.tick = 0;
+    }
+
+    /// The recorded input for the next tick, or no input once the replay has ended.
+    pub fn next_step(&mut self) -> ReplayStep {
+        let step = self
+            .replay
+            .steps
+            .get(self.tick / self.replay.ticks_per_step.max(1))
+            .copied()
+            .unwrap_or(ReplayStep::new(TileMove::None));
+        self.tick += 1;
+        step
+    }
+
+    /// Goes back one tick, for when the level was rewound by one.
+    pub fn step_back(&mut self) {
+        self.tick = self.tick.saturating_sub(1);
+    }
+
+    /// Advances `level` by one tick with the recorded input.
+    pub fn tick(&mut self, level: &mut Level) {
+        self.next_step().apply(level);
+        level.tick(self.replay.delta_time);
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "level {}", self.level + 1)?;
+        writeln!(f, "delta_time {}", self.delta_time)?;
+        writeln!(f, "ticks_per_step {}", self.ticks_per_step)?;
+        let steps: String = self.steps.iter().map(|step| step.to_char()).collect();
+        writeln!(f, "steps {steps}")
+    }
+}
+
+pub fn load_replay(path: &str) -> io::Result<Replay> {
+    Replay::parse(&fs::read_to_string(path)?)
+}
+
+pub fn save_replay(path: &str, replay: &Replay) -> io::Result<()> {
+    fs::write(path, replay.to_string())
+}
@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+
+use macroquad::prelude::Vec2;
+
+use crate::{
+    level::{GameState, Level, Player},
+    tile_data::tile::Tile,
+};
+
+/// About 30 seconds at 60 ticks per second.
+pub const REWIND_CAPACITY: usize = 1800;
+
+/// The parts of `LevelData` that change while playing.
+#[derive(Clone)]
+struct DataSnapshot {
+    game_state: GameState,
+    infotrons_required: i32,
+    red_utility_disks: u32,
+    gravity_enabled: bool,
+    zonks_frozen: bool,
+    camera_target: Option<Vec2>,
+    players: Vec<Player>,
+}
+
+impl DataSnapshot {
+    fn new(level: &Level) -> Self {
+        Self {
+            game_state: level.data.game_state,
+            infotrons_required: level.data.infotrons_required,
+            red_utility_disks: level.data.red_utility_disks,
+            gravity_enabled: level.data.gravity_enabled,
+            zonks_frozen: level.data.zonks_frozen,
+            camera_target: level.data.camera_target,
+            players: level.data.players.clone(),
+        }
+    }
+
+    fn restore(&self, level: &mut Level) {
+        level.data.game_state = self.game_state;
+        level.data.infotrons_required = self.infotrons_required;
+        level.data.red_utility_disks = self.red_utility_disks;
+        level.data.gravity_enabled = self.gravity_enabled;
+        level.data.zonks_frozen = self.zonks_frozen;
+        level.data.camera_target = self.camera_target;
+        level.data.players = self.players.clone();
+        // keys held back then are not held now
+        for player in &mut level.data.players {
+            player.move_priority.clear();
+        }
+    }
+}
+
+/// Undoes one tick: the tiles that changed during it, with their previous values.
+struct RewindEntry {
+    tiles: Vec<(usize, Tile)>,
+    data: DataSnapshot,
+}
+
+/// Ring buffer of per-tick snapshots, each storing only the tiles that changed.
+pub struct RewindBuffer {
+    entries: VecDeque<RewindEntry>,
+    capacity: usize,
+    last_tiles: Vec<Tile>,
+    last_data: Option<DataSnapshot>,
+}
+
+impl RewindBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            last_tiles: Vec::new(),
+            last_data: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Forgets the history and starts recording from `level`.
+    pub fn reset(&mut self, level: &Level) {
+        self.entries.clear();
+        self.last_tiles = level.grid.array.clone();
+        self.last_data = Some(DataSnapshot::new(level));
+    }
+
+    /// Call once after every tick of `level`.
+    pub fn record(&mut self, level: &Level) {
+        let Some(last_data) = self.last_data.replace(DataSnapshot::new(level)) else {
+            self.last_tiles = level.grid.array.clone();
+            return;
+        };
+
+        let tiles = level
+            .grid
+            .array
+            .iter()
+            .zip(self.last_tiles.iter_mut())
+            .enumerate()
+            .filter(|(_, (tile, last))| tile != last)
+            .map(|(i, (tile, last))| (i, std::mem::replace(last, tile.clone())))
+            .collect();
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(RewindEntry {
+            tiles,
+            data: last_data,
+        });
+    }
+
+    /// Steps `level` back by one recorded tick, returns false when there is nothing left.
+    pub fn rewind(&mut self, level: &mut Level) -> bool {
+        let Some(entry) = self.entries.pop_back() else {
+            return false;
+        };
+
+        for (i, tile) in entry.tiles {
+            self.last_tiles[i] = tile.clone();
+            level.grid.array[i] = tile;
+        }
+        level.grid.wake_all();
+        entry.data.restore(level);
+        self.last_data = Some(entry.data);
+
+        true
+    }
+}
@@ -0,0 +1,408 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    grid::{Coord, Grid},
+    level::{ExitRule, GameState, Level, LevelData, Player, SpecialPort},
+    level_loader::invalid_data,
+    tile_data::{
+        tile::Tile, tile_interaction::TileInteraction, tile_move::TileMove,
+        tile_state::TileState, tile_type::TileType,
+    },
+};
+
+pub const SAVES_DIR: &str = "saves";
+pub const SAVE_SLOTS: usize = 10;
+/// The slot used by quick-save and quick-load.
+pub const QUICK_SLOT: usize = 0;
+
+const SAVE_MAGIC: &[u8; 6] = b"SPSAVE";
+/// Version 2 added the players, version 1 saves load without them.
+const SAVE_VERSION: u8 = 2;
+
+/// A running level together with where it came from, so loading can select it again.
+pub struct SaveState {
+    pub pack_path: String,
+    pub level_index: usize,
+    pub level: Level,
+}
+
+pub fn slot_path(slot: usize) -> PathBuf {
+    PathBuf::from(SAVES_DIR).join(format!("slot{}.sav", slot + 1))
+}
+
+pub fn save_state(path: &Path, state: &SaveState) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, state.to_bytes()?)
+}
+
+pub fn load_state(path: &Path) -> io::Result<SaveState> {
+    SaveState::from_bytes(&fs::read(path)?)
+}
+
+impl SaveState {
+    /// Little endian throughout: header, level data, players, then every tile with its full
+    /// state. Player input is not kept, like the input of a level without players. Fails with
+    /// `InvalidInput` for more than 255 special ports or players, their counts are one byte.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let data = &self.level.data;
+        let mut bytes = SAVE_MAGIC.to_vec();
+        bytes.push(SAVE_VERSION);
+
+        write_str(&mut bytes, &self.pack_path);
+        bytes.extend((self.level_index as u32).to_le_bytes());
+
+        write_str(&mut bytes, &data.name);
+        bytes.extend(data.speed.to_le_bytes());
+        bytes.push(game_state_to_u8(data.game_state));
+        bytes.extend(data.infotrons_required.to_le_bytes());
+        bytes.extend(data.red_utility_disks.to_le_bytes());
+        bytes.push(data.gravity_enabled as u8);
+        bytes.push(data.zonks_frozen as u8);
+
+        bytes.push(count_u8(data.special_ports.len(), "special ports")?);
+        for port in &data.special_ports {
+            bytes.extend(port.coord.x.to_le_bytes());
+            bytes.extend(port.coord.y.to_le_bytes());
+            bytes.push(port.gravity_enabled as u8);
+            bytes.push(port.zonks_frozen as u8);
+            bytes.push(port.enemies_frozen as u8);
+        }
+
+        bytes.push(exit_rule_to_u8(data.exit_rule));
+        bytes.push(count_u8(data.players.len(), "players")?);
+        for player in &data.players {
+            bytes.extend(player.coord.x.to_le_bytes());
+            bytes.extend(player.coord.y.to_le_bytes());
+            bytes.push(player.exited as u8);
+        }
+
+        let grid = &self.level.grid;
+        bytes.extend(grid.width.to_le_bytes());
+        bytes.extend(grid.height.to_le_bytes());
+        for tile in &grid.array {
+            bytes.push(tile.typ.index());
+            bytes.push(state_to_u8(tile.state));
+            bytes.push(move_to_u8(tile.mov));
+            bytes.push(move_to_u8(tile.mov2));
+            let (interaction, typ) = interaction_to_u8(tile.int);
+            bytes.push(interaction);
+            bytes.push(typ.index());
+            bytes.extend(tile.upd.to_le_bytes());
+        }
+
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(SAVE_MAGIC.len())? != SAVE_MAGIC {
+            return Err(invalid_data("not a save file"));
+        }
+        let version = reader.u8()?;
+        if !(1..=SAVE_VERSION).contains(&version) {
+            return Err(invalid_data(format!("unsupported save version {version}")));
+        }
+
+        let pack_path = reader.string()?;
+        let level_index = reader.u32()? as usize;
+
+        let name = reader.string()?;
+        let speed = f32::from_bits(reader.u32()?);
+        let game_state = game_state_from_u8(reader.u8()?)?;
+        let infotrons_required = reader.u32()? as i32;
+        let red_utility_disks = reader.u32()?;
+        let gravity_enabled = reader.bool()?;
+        let zonks_frozen = reader.bool()?;
+
+        let port_count = reader.u8()?;
+        let mut special_ports = Vec::with_capacity(port_count as usize);
+        for _ in 0..port_count {
+            special_ports.push(SpecialPort {
+                coord: Coord::new(reader.i16()?, reader.i16()?),
+                gravity_enabled: reader.bool()?,
+                zonks_frozen: reader.bool()?,
+                enemies_frozen: reader.bool()?,
+            });
+        }
+
+        let mut exit_rule = ExitRule::Any;
+        let mut players = Vec::new();
+        if version >= 2 {
+            exit_rule = exit_rule_from_u8(reader.u8()?)?;
+            let player_count = reader.u8()?;
+            for _ in 0..player_count {
+                let mut player = Player::new(Coord::new(reader.i16()?, reader.i16()?));
+                player.exited = reader.bool()?;
+                players.push(player);
+            }
+        }
+
+        let width = reader.i16()?;
+        let height = reader.i16()?;
+        if width <= 0 || height <= 0 || width as usize * height as usize > i16::MAX as usize {
+            return Err(invalid_data("bad level size"));
+        }
+        let mut array = Vec::with_capacity(width as usize * height as usize);
+        for _ in 0..width as usize * height as usize {
+            let typ = type_from_u8(reader.u8()?)?;
+            let state = state_from_u8(reader.u8()?)?;
+            let mov = move_from_u8(reader.u8()?)?;
+            let mov2 = move_from_u8(reader.u8()?)?;
+            let int = interaction_from_u8(reader.u8()?, type_from_u8(reader.u8()?)?)?;
+            let upd = f32::from_bits(reader.u32()?);
+            array.push(Tile {
+                typ,
+                state,
+                mov,
+                mov2,
+                int,
+                upd,
+            });
+        }
+        if reader.pos != bytes.len() {
+            return Err(invalid_data("trailing bytes"));
+        }
+
+        let level = Level {
+            data: LevelData {
+                move_priority: Vec::new(),
+                slurping: false,
+                name,
+                speed,
+                delta_time: 0.0,
+                update_duration: Duration::ZERO,
+                camera_target: None,
+                game_state,
+                infotrons_required,
+                red_utility_disks,
+                gravity_enabled,
+                zonks_frozen,
+                special_ports,
+                players,
+                exit_rule,
+            },
+            grid: Grid::from_tiles(width, height, array),
+        };
+
+        Ok(Self {
+            pack_path,
+            level_index,
+            level,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| io::Error::new(ErrorKind::UnexpectedEof, "save file is truncated"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> io::Result<bool> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&mut self) -> io::Result<i16> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u16()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid_data("bad string"))
+    }
+}
+
+fn count_u8(count: usize, what: &str) -> io::Result<u8> {
+    u8::try_from(count).map_err(|_| {
+        io::Error::new(ErrorKind::InvalidInput, format!("{count} {what}, a save holds at most 255"))
+    })
+}
+
+fn write_str(bytes: &mut Vec<u8>, value: &str) {
+    let value = &value.as_bytes()[..value.len().min(u16::MAX as usize)];
+    bytes.extend((value.len() as u16).to_le_bytes());
+    bytes.extend(value);
+}
+
+fn type_from_u8(i: u8) -> io::Result<TileType> {
+    TileType::from_index(i).ok_or_else(|| invalid_data(format!("unknown tile type {i}")))
+}
+
+const fn state_to_u8(state: TileState) -> u8 {
+    match state {
+        TileState::Eatable => 0,
+        TileState::Moveable => 1,
+        TileState::Dangerous => 2,
+        TileState::Tunnelable => 3,
+        TileState::Destructible => 4,
+        TileState::Indestructible => 5,
+    }
+}
+
+fn state_from_u8(i: u8) -> io::Result<TileState> {
+    match i {
+        0 => Ok(TileState::Eatable),
+        1 => Ok(TileState::Moveable),
+        2 => Ok(TileState::Dangerous),
+        3 => Ok(TileState::Tunnelable),
+        4 => Ok(TileState::Destructible),
+        5 => Ok(TileState::Indestructible),
+        _ => Err(invalid_data(format!("unknown tile state {i}"))),
+    }
+}
+
+const fn move_to_u8(mov: TileMove) -> u8 {
+    match mov {
+        TileMove::Up => 0,
+        TileMove::Right => 1,
+        TileMove::Down => 2,
+        TileMove::Left => 3,
+        TileMove::None => 4,
+    }
+}
+
+fn move_from_u8(i: u8) -> io::Result<TileMove> {
+    match i {
+        0 => Ok(TileMove::Up),
+        1 => Ok(TileMove::Right),
+        2 => Ok(TileMove::Down),
+        3 => Ok(TileMove::Left),
+        4 => Ok(TileMove::None),
+        _ => Err(invalid_data(format!("unknown tile move {i}"))),
+    }
+}
+
+/// The interaction and the tile type it carries, `None` when it carries none.
+const fn interaction_to_u8(int: TileInteraction) -> (u8, TileType) {
+    match int {
+        TileInteraction::None => (0, TileType::None),
+        TileInteraction::Eating(typ) => (1, typ),
+        TileInteraction::Pushing => (2, TileType::None),
+        TileInteraction::Slurping => (3, TileType::None),
+        TileInteraction::Tunneling(typ) => (4, typ),
+        TileInteraction::Moving => (5, TileType::None),
+        TileInteraction::Rotating => (6, TileType::None),
+    }
+}
+
+fn interaction_from_u8(i: u8, typ: TileType) -> io::Result<TileInteraction> {
+    match i {
+        0 => Ok(TileInteraction::None),
+        1 => Ok(TileInteraction::Eating(typ)),
+        2 => Ok(TileInteraction::Pushing),
+        3 => Ok(TileInteraction::Slurping),
+        4 => Ok(TileInteraction::Tunneling(typ)),
+        5 => Ok(TileInteraction::Moving),
+        6 => Ok(TileInteraction::Rotating),
+        _ => Err(invalid_data(format!("unknown tile interaction {i}"))),
+    }
+}
+
+const fn game_state_to_u8(game_state: GameState) -> u8 {
+    match game_state {
+        GameState::Active => 0,
+        GameState::Died => 1,
+        GameState::Finished => 2,
+    }
+}
+
+fn game_state_from_u8(i: u8) -> io::Result<GameState> {
+    match i {
+        0 => Ok(GameState::Active),
+        1 => Ok(GameState::Died),
+        2 => Ok(GameState::Finished),
+        _ => Err(invalid_data(format!("unknown game state {i}"))),
+    }
+}
+
+const fn exit_rule_to_u8(exit_rule: ExitRule) -> u8 {
+    match exit_rule {
+        ExitRule::Any => 0,
+        ExitRule::All => 1,
+    }
+}
+
+fn exit_rule_from_u8(i: u8) -> io::Result<ExitRule> {
+    match i {
+        0 => Ok(ExitRule::Any),
+        1 => Ok(ExitRule::All),
+        _ => Err(invalid_data(format!("unknown exit rule {i}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_loader::parse_level_text;
+
+    #[test]
+    fn players_survive_a_save() {
+        let mut level = parse_level_text("#####\n#M.M#\n#####").unwrap();
+        level.add_players(ExitRule::All);
+        // not the order add_players finds them in, loading must not assign them again
+        level.data.players.reverse();
+        level.data.players[1].exited = true;
+        let state = SaveState {
+            pack_path: "LEVELS.DAT".to_string(),
+            level_index: 0,
+            level,
+        };
+
+        let loaded = SaveState::from_bytes(&state.to_bytes().unwrap()).unwrap();
+        let players: Vec<(Coord, bool)> = loaded
+            .level
+            .data
+            .players
+            .iter()
+            .map(|player| (player.coord, player.exited))
+            .collect();
+        assert_eq!(players, [(Coord::new(3, 1), false), (Coord::new(1, 1), true)]);
+        assert_eq!(loaded.level.data.exit_rule, ExitRule::All);
+    }
+
+    #[test]
+    fn too_many_special_ports_are_refused() {
+        let mut level = parse_level_text("special_port: 1,0\nM+").unwrap();
+        let port = level.data.special_ports[0];
+        level.data.special_ports = vec![port; 256];
+        let state = SaveState {
+            pack_path: "LEVELS.DAT".to_string(),
+            level_index: 0,
+            level,
+        };
+
+        let err = state.to_bytes().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
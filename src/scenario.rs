@@ -0,0 +1,140 @@
+//! Small levels run for a few ticks with one input held, and the tiles they must end with.
+//! They pin down the update order and edges of `Level::tick`.
+
+use crate::{level::Level, level_loader::parse_level_text, replay::ReplayStep};
+
+struct Scenario {
+    delta_time: f32,
+    ticks: usize,
+    /// A replay character, `.` for no input.
+    input: char,
+    start: &'static [&'static str],
+    expected: &'static [&'static str],
+}
+
+impl Scenario {
+    /// Runs the scenario, panics showing the tiles it ended with when they are not expected.
+    fn check(&self) {
+        let mut level = parse_level_text(&self.start.join("\n")).unwrap();
+        let step = ReplayStep::from_char(self.input).unwrap();
+        for _ in 0..self.ticks {
+            step.apply(&mut level);
+            level.tick(self.delta_time);
+        }
+
+        let rows = tile_rows(&level);
+        assert!(
+            rows == self.expected,
+            "expected:\n{}\ngot:\n{}",
+            self.expected.join("\n"),
+            rows.join("\n")
+        );
+    }
+}
+
+fn tile_rows(level: &Level) -> Vec<String> {
+    level
+        .grid
+        .array
+        .chunks(level.grid.width as usize)
+        .map(|row| row.iter().map(|tile| tile.typ.to_char()).collect())
+        .collect()
+}
+
+#[test]
+fn a_falling_zonk_moves_one_tile_per_tick() {
+    Scenario {
+        delta_time: 1.0,
+        ticks: 1,
+        input: '.',
+        start: &["#####", "# O #", "#   #", "#   #", "#####"],
+        expected: &["#####", "#   #", "# O #", "#   #", "#####"],
+    }
+    .check();
+}
+
+#[test]
+fn murphy_moves_one_tile_per_tick() {
+    Scenario {
+        delta_time: 1.0,
+        ticks: 1,
+        input: 'R',
+        start: &["######", "#M   #", "######"],
+        expected: &["######", "# M  #", "######"],
+    }
+    .check();
+}
+
+/// The zonk lands right of the scan position, where the scan reaches it again in the same
+/// tick. It must not fall before the next one.
+#[test]
+fn a_rolling_zonk_does_not_also_fall_in_the_same_tick() {
+    Scenario {
+        delta_time: 1.0,
+        ticks: 1,
+        input: '.',
+        start: &["#####", "#O  #", "#O  #", "#####"],
+        expected: &["#####", "# O #", "#O  #", "#####"],
+    }
+    .check();
+}
+
+#[test]
+fn rows_above_are_updated_before_rows_below() {
+    Scenario {
+        delta_time: 1.0,
+        ticks: 1,
+        input: '.',
+        start: &["###", "#O#", "#O#", "# #", "# #", "###"],
+        expected: &["###", "#O#", "# #", "#O#", "# #", "###"],
+    }
+    .check();
+}
+
+#[test]
+fn the_leftmost_of_two_zonks_rolls_into_a_gap_first() {
+    Scenario {
+        delta_time: 0.125,
+        ticks: 1,
+        input: '.',
+        start: &["#####", "#O O#", "#O O#", "#####"],
+        expected: &["#####", "# OO#", "#O O#", "#####"],
+    }
+    .check();
+}
+
+#[test]
+fn the_left_edge_is_a_border_not_the_end_of_the_row_above() {
+    Scenario {
+        delta_time: 0.125,
+        ticks: 1,
+        input: '.',
+        start: &["   ", "O# ", "O##", "###"],
+        expected: &["   ", "O# ", "O##", "###"],
+    }
+    .check();
+}
+
+#[test]
+fn with_gravity_murphy_falls_through_empty_space() {
+    Scenario {
+        delta_time: 1.0,
+        ticks: 1,
+        input: '.',
+        start: &["gravity: on", "###", "#M#", "# #", "# #", "###"],
+        expected: &["###", "# #", "#M#", "# #", "###"],
+    }
+    .check();
+}
+
+#[test]
+fn with_gravity_murphy_cannot_climb_empty_space() {
+    Scenario {
+        delta_time: 1.0,
+        ticks: 1,
+        input: 'U',
+        start: &["gravity: on", "###", "# #", "#M#", "###"],
+        expected: &["###", "# #", "#M#", "###"],
+    }
+    .check();
+}
@@ -0,0 +1,96 @@
+/// Which tiles `Level::tick` visits. Walls, bases and empty space never act on their own,
+/// and a zonk or disk lying still only acts again once something next to it changes, so
+/// only tiles that are moving, animating or next to a recent change are kept awake.
+///
+/// Every write through `Grid::set` is recorded, and the tick wakes the written tile and its
+/// eight neighbours. Code that writes `Grid::array` directly has to call `wake_all`.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    /// One bit per tile in row-major order.
+    awake: Vec<u64>,
+    /// Tiles written this tick further along the scan, which are not updated again.
+    updated: Vec<u64>,
+    changed: Vec<u32>,
+    stale: bool,
+}
+
+impl Schedule {
+    /// Starts out stale, so the first tick looks at every tile.
+    pub fn new(len: usize) -> Self {
+        Self {
+            awake: vec![0; len.div_ceil(64)],
+            updated: vec![0; len.div_ceil(64)],
+            changed: Vec::new(),
+            stale: true,
+        }
+    }
+
+    /// Makes the next tick look at every tile again.
+    pub fn wake_all(&mut self) {
+        self.stale = true;
+    }
+
+    /// Whether the tiles have to be looked at again, clears the flag.
+    pub fn take_stale(&mut self) -> bool {
+        std::mem::replace(&mut self.stale, false)
+    }
+
+    pub fn record_change(&mut self, index: usize) {
+        self.changed.push(index as u32);
+    }
+
+    /// Moves the tiles written since the last call into `changed`, which should be empty.
+    pub fn take_changes(&mut self, changed: &mut Vec<u32>) {
+        std::mem::swap(&mut self.changed, changed);
+    }
+
+    pub fn clear(&mut self) {
+        self.awake.fill(0);
+        self.changed.clear();
+    }
+
+    /// Forgets which tiles were updated, at the start of a tick.
+    pub fn clear_updated(&mut self) {
+        self.updated.fill(0);
+    }
+
+    pub fn mark_updated(&mut self, index: usize) {
+        self.updated[index / 64] |= 1 << (index % 64);
+    }
+
+    pub fn is_updated(&self, index: usize) -> bool {
+        self.updated[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn is_awake(&self, index: usize) -> bool {
+        self.awake[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Returns true when the tile was asleep.
+    pub fn wake(&mut self, index: usize) -> bool {
+        let asleep = !self.is_awake(index);
+        self.awake[index / 64] |= 1 << (index % 64);
+        asleep
+    }
+
+    pub fn sleep(&mut self, index: usize) {
+        self.awake[index / 64] &= !(1 << (index % 64));
+    }
+
+    /// The first awake tile at or after `from`.
+    pub fn next_awake(&self, from: usize) -> Option<usize> {
+        let mut word = from / 64;
+        let mut bits = *self.awake.get(word)? & (!0 << (from % 64));
+        loop {
+            if bits != 0 {
+                return Some(word * 64 + bits.trailing_zeros() as usize);
+            }
+            word += 1;
+            bits = *self.awake.get(word)?;
+        }
+    }
+
+    pub fn awake_count(&self) -> usize {
+        self.awake.iter().map(|bits| bits.count_ones() as usize).sum()
+    }
+}
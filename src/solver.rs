@@ -0,0 +1,224 @@
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::DefaultHasher, BinaryHeap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    grid::Coord,
+    level::{GameState, Level},
+    replay::{Replay, ReplayStep, REPLAY_DELTA_TIME, REPLAY_TICKS_PER_STEP},
+    tile_data::{tile::Tile, tile_move::TileMove, tile_type::TileType},
+};
+
+/// Every input a replay step can hold, each move also with Space held to slurp.
+const ACTIONS: [ReplayStep; 9] = [
+    ReplayStep::new(TileMove::Up),
+    ReplayStep::new(TileMove::Right),
+    ReplayStep::new(TileMove::Down),
+    ReplayStep::new(TileMove::Left),
+    ReplayStep::new(TileMove::None),
+    ReplayStep::slurp(TileMove::Up),
+    ReplayStep::slurp(TileMove::Right),
+    ReplayStep::slurp(TileMove::Down),
+    ReplayStep::slurp(TileMove::Left),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SolveError {
+    /// Every reachable state was explored without reaching the exit.
+    Unsolvable { explored: usize },
+    /// Gave up after exploring `explored` states.
+    LimitReached { explored: usize },
+}
+
+/// A state reached by the search, kept small: only the tiles its step changed and the
+/// counters. The level itself is rebuilt from the start when the node is expanded.
+struct Node {
+    parent: Option<usize>,
+    step: ReplayStep,
+    /// The tiles that changed during `step`, with their new values.
+    tiles: Vec<(u32, Tile)>,
+    infotrons_required: i32,
+    red_utility_disks: u32,
+    gravity_enabled: bool,
+    zonks_frozen: bool,
+}
+
+impl Node {
+    fn new(parent: Option<usize>, step: ReplayStep, before: &Level, after: &Level) -> Self {
+        let tiles = before
+            .grid
+            .array
+            .iter()
+            .zip(&after.grid.array)
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(i, (_, after))| (i as u32, after.clone()))
+            .collect();
+        Self {
+            parent,
+            step,
+            tiles,
+            infotrons_required: after.data.infotrons_required,
+            red_utility_disks: after.data.red_utility_disks,
+            gravity_enabled: after.data.gravity_enabled,
+            zonks_frozen: after.data.zonks_frozen,
+        }
+    }
+}
+
+/// The level of `nodes[index]`, by applying the changes of every step on the way there.
+fn rebuild(start: &Level, nodes: &[Node], index: usize) -> Level {
+    let mut path = Vec::new();
+    let mut current = Some(index);
+    while let Some(i) = current {
+        path.push(i);
+        current = nodes[i].parent;
+    }
+
+    let mut level = start.clone();
+    for &i in path.iter().rev() {
+        let node = &nodes[i];
+        for (tile_index, tile) in &node.tiles {
+            level.grid.array[*tile_index as usize] = tile.clone();
+        }
+        level.data.infotrons_required = node.infotrons_required;
+        level.data.red_utility_disks = node.red_utility_disks;
+        level.data.gravity_enabled = node.gravity_enabled;
+        level.data.zonks_frozen = node.zonks_frozen;
+    }
+    level.grid.wake_all();
+    level
+}
+
+/// A* over the states reached by holding one input for a replay step, with the manhattan
+/// distance from Murphy to the nearest infotron (or exit once enough are collected) as heuristic.
+pub fn solve(level: &Level, level_index: usize, max_states: usize) -> Result<Replay, SolveError> {
+    let mut nodes = vec![Node::new(None, ReplayStep::new(TileMove::None), level, level)];
+    let mut open = BinaryHeap::new();
+    let mut seen = HashSet::new();
+
+    seen.insert(state_hash(level));
+    open.push(Reverse((heuristic(level), 0_usize, 0_usize)));
+
+    while let Some(Reverse((_, cost, index))) = open.pop() {
+        let state = rebuild(level, &nodes, index);
+
+        for step in ACTIONS {
+            let mut next = state.clone();
+            step.play(&mut next, REPLAY_DELTA_TIME, REPLAY_TICKS_PER_STEP);
+
+            match next.data.game_state {
+                GameState::Died => continue,
+                GameState::Finished => {
+                    let mut steps = vec![step];
+                    let mut current = Some(index);
+                    while let Some(i) = current {
+                        if nodes[i].parent.is_some() {
+                            steps.push(nodes[i].step);
+                        }
+                        current = nodes[i].parent;
+                    }
+                    steps.reverse();
+                    return Ok(Replay::new(level_index, steps));
+                }
+                GameState::Active => {}
+            }
+
+            if !seen.insert(state_hash(&next)) {
+                continue;
+            }
+            if seen.len() > max_states {
+                return Err(SolveError::LimitReached {
+                    explored: seen.len(),
+                });
+            }
+
+            let priority = cost + 1 + heuristic(&next);
+            open.push(Reverse((priority, cost + 1, nodes.len())));
+            nodes.push(Node::new(Some(index), step, &state, &next));
+        }
+    }
+
+    Err(SolveError::Unsolvable {
+        explored: seen.len(),
+    })
+}
+
+/// Everything a step can change, timers included, so only states that play on exactly
+/// alike are treated as one.
+pub fn state_hash(level: &Level) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    level.grid.width.hash(&mut hasher);
+    for tile in &level.grid.array {
+        tile.typ.hash(&mut hasher);
+        tile.state.hash(&mut hasher);
+        tile.mov.hash(&mut hasher);
+        tile.mov2.hash(&mut hasher);
+        tile.int.hash(&mut hasher);
+        tile.upd.to_bits().hash(&mut hasher);
+    }
+    level.data.infotrons_required.hash(&mut hasher);
+    level.data.red_utility_disks.hash(&mut hasher);
+    level.data.game_state.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn heuristic(level: &Level) -> usize {
+    let target = if level.data.infotrons_required > 0 {
+        TileType::Infotron
+    } else {
+        TileType::Exit
+    };
+
+    let mut murphy = None;
+    let mut targets = Vec::new();
+    for y in 0..level.grid.height {
+        for x in 0..level.grid.width {
+            let coord = Coord::new(x, y);
+            match level.grid.get(&coord).typ {
+                TileType::Murphy => murphy = Some(coord),
+                typ if typ == target => targets.push(coord),
+                _ => {}
+            }
+        }
+    }
+
+    let Some(murphy) = murphy else {
+        return 0;
+    };
+    targets
+        .iter()
+        .map(|coord| (coord.x.abs_diff(murphy.x) + coord.y.abs_diff(murphy.y)) as usize)
+        .min()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_loader::parse_level_text;
+
+    #[test]
+    fn an_exit_next_to_murphy_takes_one_step() {
+        let level = parse_level_text("######\n#ME  #\n######").unwrap();
+        let replay = solve(&level, 3, 1000).unwrap();
+        assert_eq!(replay.level, 3);
+        assert_eq!(replay.steps, [ReplayStep::new(TileMove::Right)]);
+    }
+
+    #[test]
+    fn the_exit_is_only_reached_after_the_infotrons() {
+        let level = parse_level_text("infotrons: 1\n#######\n#*ME  #\n#######").unwrap();
+        let replay = solve(&level, 0, 1000).unwrap();
+        assert_eq!(replay.steps.last(), Some(&ReplayStep::new(TileMove::Right)));
+        assert!(replay.steps.len() >= 2);
+    }
+
+    #[test]
+    fn a_walled_in_murphy_is_unsolvable() {
+        let level = parse_level_text("#####\n#M#E#\n#####").unwrap();
+        assert!(matches!(solve(&level, 0, 1000), Err(SolveError::Unsolvable { .. })));
+    }
+}
@@ -0,0 +1,285 @@
+use std::sync::OnceLock;
+
+use crate::{
+    grid::{Coord, FCoord, Grid},
+    level::{GameState, LevelData},
+};
+
+use super::{
+    sprite::SpriteTarget,
+    systems::{
+        ai_system::SnikSnakBehavior,
+        explode_system::ExplosionBehavior,
+        gravity_system::{InfotronBehavior, ZonkBehavior},
+        murphy_system::MurphyBehavior,
+        orange_disk_system::OrangeDiskBehavior,
+        transitory_system::TransitoryBehavior,
+    },
+    tile::Tile,
+    tile_move::TileMove,
+    tile_state::TileState,
+    tile_type::{simple_draw, TileType},
+    tile_update::Updateable,
+};
+
+/// What an explosion next to a tile turns it into.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Blast {
+    /// The tile survives.
+    Keep,
+    /// The tile is destroyed.
+    Explode,
+    /// The tile is destroyed and explodes itself once the blast has burnt out.
+    Chain,
+}
+
+/// How one type of tile acts. Everything has a default, so walls and other static tiles
+/// only need an empty impl.
+pub trait TileBehavior: Send + Sync {
+    /// Whether the `upd` timer of these tiles counts down every tick.
+    fn animated(&self) -> bool {
+        false
+    }
+
+    /// Updated every tick, even while nothing around it changes. Other tiles are put to
+    /// sleep once an update leaves them in place and unchanged with a timer of 0, and are
+    /// woken when one of their eight neighbours changes, so their update must not read
+    /// anything further away.
+    fn always_active(&self) -> bool {
+        false
+    }
+
+    /// Runs once per tick for the tile at `coord`, returns where it is afterwards. Other
+    /// tiles must only be changed through `Grid::set`.
+    fn update(&self, coord: &Coord, _grid: &mut Grid, _level_data: &mut LevelData) -> Coord {
+        *coord
+    }
+
+    fn draw(&self, tile: &Tile, fcoord: &FCoord, mut target: &mut dyn SpriteTarget) {
+        simple_draw(&mut target, fcoord, &tile.typ.def_tex_pos(), false);
+    }
+
+    /// Called for every tile around an explosion, the tile at its centre always explodes.
+    fn on_explode(&self, tile: &Tile, _game_state: &mut GameState) -> Blast {
+        match tile.state {
+            TileState::Indestructible => Blast::Keep,
+            _ => Blast::Explode,
+        }
+    }
+
+    /// Called when Murphy eats the tile, before it is removed.
+    fn on_eaten(&self, _level_data: &mut LevelData) {}
+
+    /// Whether Murphy can push the tile moving `mov`, the tile behind it must also be empty.
+    fn on_pushed(&self, _tile: &Tile, mov: TileMove) -> bool {
+        matches!(mov, TileMove::Left | TileMove::Right)
+    }
+}
+
+/// Walls, bases, ports and the exit.
+pub struct StaticBehavior;
+
+impl TileBehavior for StaticBehavior {}
+
+/// Empty space and the outside of the level, nothing is drawn.
+pub struct EmptyBehavior;
+
+impl TileBehavior for EmptyBehavior {
+    fn draw(&self, _tile: &Tile, _fcoord: &FCoord, _target: &mut dyn SpriteTarget) {}
+}
+
+/// Tiles that keep a timer but do not act on their own yet, like bugs and terminals.
+pub struct IdleBehavior;
+
+impl TileBehavior for IdleBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, _level_data: &mut LevelData) -> Coord {
+        settle(coord, grid)
+    }
+}
+
+/// Stops the timer of a tile that only animates at 0, so it can go to sleep.
+fn settle(coord: &Coord, grid: &mut Grid) -> Coord {
+    if let Some(tile) = grid.get_mut(coord) {
+        if tile.upd < 0.0 {
+            tile.upd.dont_update();
+        }
+    }
+    *coord
+}
+
+pub struct ElectronBehavior;
+
+impl TileBehavior for ElectronBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, _level_data: &mut LevelData) -> Coord {
+        settle(coord, grid)
+    }
+
+    fn on_explode(&self, _tile: &Tile, _game_state: &mut GameState) -> Blast {
+        Blast::Chain
+    }
+}
+
+pub struct YellowDiskBehavior;
+
+impl TileBehavior for YellowDiskBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, _level_data: &mut LevelData) -> Coord {
+        settle(coord, grid)
+    }
+
+    fn draw(&self, tile: &Tile, fcoord: &FCoord, mut target: &mut dyn SpriteTarget) {
+        let fcoord = fcoord.offset_time(tile);
+        simple_draw(&mut target, &fcoord, &tile.typ.def_tex_pos(), false);
+    }
+
+    fn on_explode(&self, _tile: &Tile, _game_state: &mut GameState) -> Blast {
+        Blast::Chain
+    }
+}
+
+pub struct RedDiskBehavior;
+
+impl TileBehavior for RedDiskBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, _level_data: &mut LevelData) -> Coord {
+        settle(coord, grid)
+    }
+
+    fn on_eaten(&self, level_data: &mut LevelData) {
+        level_data.red_utility_disks += 1;
+    }
+}
+
+/// The behaviour of every tile type, looked up by `TileType::index`.
+pub struct TileRegistry {
+    behaviors: Vec<Box<dyn TileBehavior>>,
+}
+
+impl TileRegistry {
+    /// Every type acting like a wall, to register behaviours into.
+    pub fn empty() -> Self {
+        Self {
+            behaviors: (0..TileType::COUNT)
+                .map(|_| Box::new(StaticBehavior) as Box<dyn TileBehavior>)
+                .collect(),
+        }
+    }
+
+    pub fn register(&mut self, typ: TileType, behavior: impl TileBehavior + 'static) {
+        self.behaviors[typ.index() as usize] = Box::new(behavior);
+    }
+
+    pub fn get(&self, typ: TileType) -> &dyn TileBehavior {
+        self.behaviors[typ.index() as usize].as_ref()
+    }
+}
+
+impl Default for TileRegistry {
+    /// The tiles of the original game.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(TileType::None, EmptyBehavior);
+        registry.register(TileType::Empty, EmptyBehavior);
+        registry.register(TileType::Murphy, MurphyBehavior);
+        registry.register(TileType::Infotron, InfotronBehavior);
+        registry.register(TileType::Zonk, ZonkBehavior);
+        registry.register(TileType::SnikSnak, SnikSnakBehavior);
+        registry.register(TileType::Electron, ElectronBehavior);
+        registry.register(TileType::Bug, IdleBehavior);
+        registry.register(TileType::Terminal, IdleBehavior);
+        registry.register(TileType::OrangeUtilityDisk, OrangeDiskBehavior);
+        registry.register(TileType::YellowUtilityDisk, YellowDiskBehavior);
+        registry.register(TileType::RedUtilityDisk, RedDiskBehavior);
+        registry.register(TileType::Transitory, TransitoryBehavior);
+        registry.register(TileType::Explosion, ExplosionBehavior);
+        registry.register(TileType::Explosion2, ExplosionBehavior);
+        registry
+    }
+}
+
+static REGISTRY: OnceLock<TileRegistry> = OnceLock::new();
+
+/// The registry every level runs with, the default one unless `set_registry` came first.
+pub fn registry() -> &'static TileRegistry {
+    REGISTRY.get_or_init(TileRegistry::default)
+}
+
+/// Replaces the tile behaviours, for mods. Only works before the first level is updated or
+/// drawn, otherwise `registry` is handed back.
+pub fn set_registry(registry: TileRegistry) -> Result<(), TileRegistry> {
+    REGISTRY.set(registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_loader::parse_level_text;
+
+    fn level_data() -> LevelData {
+        parse_level_text("infotrons: 2\nM**").unwrap().data
+    }
+
+    #[test]
+    fn murphy_dies_and_chains() {
+        let registry = TileRegistry::default();
+        let mut game_state = GameState::Active;
+        let blast = registry
+            .get(TileType::Murphy)
+            .on_explode(&TileType::Murphy.to_tile(), &mut game_state);
+        assert_eq!(blast, Blast::Chain);
+        assert_eq!(game_state, GameState::Died);
+    }
+
+    #[test]
+    fn eating_an_infotron_counts_it() {
+        let registry = TileRegistry::default();
+        let mut level_data = level_data();
+        registry.get(TileType::Infotron).on_eaten(&mut level_data);
+        assert_eq!(level_data.infotrons_required, 1);
+    }
+
+    /// A bug that cannot be blown up and hands out a red disk when eaten.
+    struct ArmouredBug;
+
+    impl TileBehavior for ArmouredBug {
+        fn on_explode(&self, _tile: &Tile, _game_state: &mut GameState) -> Blast {
+            Blast::Keep
+        }
+
+        fn on_eaten(&self, level_data: &mut LevelData) {
+            level_data.red_utility_disks += 1;
+        }
+    }
+
+    #[test]
+    fn registered_behavior_replaces_the_default() {
+        let mut registry = TileRegistry::default();
+        registry.register(TileType::Bug, ArmouredBug);
+
+        let bug = TileType::Bug.to_tile();
+        let mut game_state = GameState::Active;
+        assert_eq!(registry.get(TileType::Bug).on_explode(&bug, &mut game_state), Blast::Keep);
+        let mut level_data = level_data();
+        registry.get(TileType::Bug).on_eaten(&mut level_data);
+        assert_eq!(level_data.red_utility_disks, 1);
+
+        // other types keep their behaviour
+        let base = TileType::Base.to_tile();
+        assert_eq!(registry.get(TileType::Base).on_explode(&base, &mut game_state), Blast::Explode);
+        assert_eq!(game_state, GameState::Active);
+    }
+}
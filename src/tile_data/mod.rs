@@ -1,7 +1,9 @@
+pub mod behavior;
 pub mod tile;
 pub mod tile_type;
 pub mod tile_state;
 pub mod tile_move;
 pub mod tile_update;
 pub mod tile_interaction;
-pub mod systems;
\ No newline at end of file
+pub mod systems;
+pub mod sprite;
\ No newline at end of file
@@ -0,0 +1,51 @@
+use macroquad::{
+    prelude::{Rect, Vec2, WHITE},
+    texture::{draw_texture_ex, DrawTextureParams, Texture2D},
+};
+
+use crate::grid::{Coord, FCoord};
+
+pub const DEFAULT_SPRITE_SHEET: &str = "assets/moving2.png";
+
+/// Size of a tile in pixels, in the sprite sheet and in the world.
+pub const SPRITE_SIZE: u32 = 16;
+/// Distance between two sprites in the sheet, they are separated by a 1 pixel gap.
+pub const SPRITE_STRIDE: u32 = 17;
+
+/// Something tiles can be drawn onto, the window through a sprite sheet texture or an image
+/// in memory, so both go through the same sprite lookup.
+pub trait SpriteTarget {
+    /// Draws the sprite at `src` in the sheet with its top left corner at `dst`, both in tiles.
+    fn draw_sprite(&mut self, dst: &FCoord, src: &Coord, flip_x: bool, flip_y: bool);
+}
+
+impl<T: SpriteTarget + ?Sized> SpriteTarget for &mut T {
+    fn draw_sprite(&mut self, dst: &FCoord, src: &Coord, flip_x: bool, flip_y: bool) {
+        (**self).draw_sprite(dst, src, flip_x, flip_y);
+    }
+}
+
+impl SpriteTarget for Texture2D {
+    fn draw_sprite(&mut self, dst: &FCoord, src: &Coord, flip_x: bool, flip_y: bool) {
+        let size = SPRITE_SIZE as f32;
+        draw_texture_ex(
+            *self,
+            dst.x * size,
+            dst.y * size,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(Vec2 { x: size, y: size }),
+                source: Some(Rect {
+                    x: (src.x as u32 * SPRITE_STRIDE) as f32,
+                    y: (src.y as u32 * SPRITE_STRIDE) as f32,
+                    w: size,
+                    h: size,
+                }),
+                rotation: 0.0,
+                flip_x,
+                flip_y,
+                pivot: None,
+            },
+        );
+    }
+}
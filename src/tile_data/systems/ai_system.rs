@@ -1,12 +1,9 @@
-use macroquad::{
-    prelude::{Rect, Vec2, WHITE},
-    texture::{draw_texture_ex, DrawTextureParams, Texture2D},
-};
-
 use crate::{
     grid::{Coord, FCoord, Grid},
-    level::LevelData,
+    level::{GameState, LevelData},
     tile_data::{
+        behavior::{Blast, TileBehavior},
+        sprite::SpriteTarget,
         tile::Tile, tile_interaction::TileInteraction, tile_move::TileMove, tile_type::TileType,
         tile_update::Updateable,
     },
@@ -14,7 +11,28 @@ use crate::{
 
 use super::explode_system::set_area_explode;
 
-pub fn ai_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) {
+pub struct SnikSnakBehavior;
+
+impl TileBehavior for SnikSnakBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
+        ai_system(coord, grid, level_data)
+    }
+
+    fn draw(&self, tile: &Tile, fcoord: &FCoord, mut target: &mut dyn SpriteTarget) {
+        draw_ai_system(tile, fcoord, &mut target);
+    }
+
+    fn on_explode(&self, _tile: &Tile, _game_state: &mut GameState) -> Blast {
+        Blast::Chain
+    }
+}
+
+/// Turns and moves a snik snak along the wall on its left, returns where it went.
+pub fn ai_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
     let ai_tile = grid.get(coord);
 
     let left = ai_tile.mov.rotate_left();
@@ -27,10 +45,7 @@ pub fn ai_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) {
     if ai_tile.upd <= 0.0 {
         if ai_tile.mov == ai_tile.mov2 {
             if grid.get(coord_left).typ == TileType::Empty {
-                let ai = grid.get_mut_unchecked(coord);
-                ai.upd += 0.25;
-                ai.mov = left;
-                ai.int = TileInteraction::Rotating;
+                rotate(grid, coord, left);
             } else if grid.get(coord_left).typ == TileType::Murphy {
                 set_area_explode(coord, grid, &mut level_data.game_state);
             } else if grid.get(coord_front).typ == TileType::Empty {
@@ -44,20 +59,15 @@ pub fn ai_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) {
                 );
                 grid.set(coord_front, ai);
                 grid.set(coord, Tile::transitory(upd, TileType::Empty));
+                return *coord_front;
             } else if grid.get(coord_front).typ == TileType::Murphy {
                 set_area_explode(coord, grid, &mut level_data.game_state);
             } else if grid.get(coord_right).typ == TileType::Empty {
-                let ai = grid.get_mut_unchecked(coord);
-                ai.upd += 0.25;
-                ai.mov = right;
-                ai.int = TileInteraction::Rotating;
+                rotate(grid, coord, right);
             } else if grid.get(coord_right).typ == TileType::Murphy {
                 set_area_explode(coord, grid, &mut level_data.game_state);
             } else {
-                let ai = grid.get_mut_unchecked(coord);
-                ai.upd += 0.25;
-                ai.mov = left;
-                ai.int = TileInteraction::Rotating;
+                rotate(grid, coord, left);
             }
         } else if grid.get(coord_front).typ == TileType::Empty {
             let upd = ai_tile.upd.move_update();
@@ -70,38 +80,41 @@ pub fn ai_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) {
             );
             grid.set(coord_front, ai);
             grid.set(coord, Tile::transitory(upd, TileType::Empty));
+            return *coord_front;
         } else if grid.get(coord_front).typ == TileType::Murphy {
             set_area_explode(coord, grid, &mut level_data.game_state);
         } else {
-            let ai = grid.get_mut_unchecked(coord);
-            ai.upd += 0.25;
-            ai.mov2 = ai.mov;
-            ai.int = TileInteraction::Rotating;
+            grid.modify(coord, |ai| {
+                ai.upd += 0.25;
+                ai.mov2 = ai.mov;
+                ai.int = TileInteraction::Rotating;
+            });
         }
     } else if ai_tile.upd <= 0.125 && ai_tile.mov == ai_tile.mov2 {
         if grid.get(coord_left).typ == TileType::Empty || grid.get(coord_left).typ == TileType::Murphy {
-            let ai = grid.get_mut_unchecked(coord);
-            ai.upd += 0.25;
-            ai.mov = left;
-            ai.int = TileInteraction::Rotating;
+            rotate(grid, coord, left);
         } else if grid.get(coord_front).typ == TileType::Empty || grid.get(coord_front).typ == TileType::Murphy {
         } else if grid.get(coord_right).typ == TileType::Empty || grid.get(coord_right).typ == TileType::Murphy {
-            let ai = grid.get_mut_unchecked(coord);
-            ai.upd += 0.25;
-            ai.mov = right;
-            ai.int = TileInteraction::Rotating;
+            rotate(grid, coord, right);
         } else {
-            let ai = grid.get_mut_unchecked(coord);
-            ai.upd += 0.25;
-            ai.mov = left;
-            ai.int = TileInteraction::Rotating;
+            rotate(grid, coord, left);
         }
     } else if grid.get(coord).upd < 0.0 {
-        grid.get_mut_unchecked(coord).upd.dont_update();
+        grid.modify(coord, |ai| ai.upd.dont_update());
     }
+    *coord
+}
+
+/// Starts turning a snik snak towards `mov`.
+fn rotate(grid: &mut Grid, coord: &Coord, mov: TileMove) {
+    grid.modify(coord, |ai| {
+        ai.upd += 0.25;
+        ai.mov = mov;
+        ai.int = TileInteraction::Rotating;
+    });
 }
 
-pub fn draw_ai_system(ai: &Tile, fcoord: &FCoord, texture: Texture2D) {
+pub fn draw_ai_system(ai: &Tile, fcoord: &FCoord, target: &mut impl SpriteTarget) {
     let x = match (ai.mov, ai.mov2) {
         (TileMove::Up, TileMove::Right) => 3,
         (TileMove::Up, TileMove::Left) => 0,
@@ -141,23 +154,5 @@ pub fn draw_ai_system(ai: &Tile, fcoord: &FCoord, texture: Texture2D) {
         *fcoord
     };
 
-    draw_texture_ex(
-        texture,
-        smooth_dst.x * 16.0,
-        smooth_dst.y * 16.0,
-        WHITE,
-        DrawTextureParams {
-            dest_size: Some(Vec2 { x: 16.0, y: 16.0 }),
-            source: Some(Rect {
-                x: (x + offset) as f32 * 17.0,
-                y: 12_f32 * 17.0,
-                w: 16.0,
-                h: 16.0,
-            }),
-            rotation: 0.0,
-            flip_x,
-            flip_y,
-            pivot: None,
-        },
-    );
+    target.draw_sprite(&smooth_dst, &Coord::new(x + offset, 12), flip_x, flip_y);
 }
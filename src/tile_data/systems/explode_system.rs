@@ -1,9 +1,9 @@
-use macroquad::texture::Texture2D;
-
 use crate::{
     grid::{Coord, FCoord, Grid},
     level::{LevelData, GameState},
     tile_data::{
+        behavior::{registry, Blast, TileBehavior},
+        sprite::SpriteTarget,
         tile::Tile,
         tile_interaction::TileInteraction,
         tile_move::TileMove,
@@ -12,6 +12,31 @@ use crate::{
     },
 };
 
+/// Both kinds of explosion, the second one sets off another blast when it burns out.
+pub struct ExplosionBehavior;
+
+impl TileBehavior for ExplosionBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
+        explode_system(coord, grid, level_data);
+        *coord
+    }
+
+    fn draw(&self, tile: &Tile, fcoord: &FCoord, mut target: &mut dyn SpriteTarget) {
+        draw_explode_system(tile, fcoord, &mut target);
+    }
+
+    fn on_explode(&self, tile: &Tile, _game_state: &mut GameState) -> Blast {
+        match tile.typ {
+            TileType::Explosion2 => Blast::Keep,
+            _ => Blast::Explode,
+        }
+    }
+}
+
 pub fn explode_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) {
     let explosion = grid.get(coord);
 
@@ -30,20 +55,44 @@ pub fn explode_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData
     }
 }
 
-pub fn draw_explode_system(explosion: &Tile, fcoord: &FCoord, texture: Texture2D) {
+pub fn draw_explode_system(explosion: &Tile, fcoord: &FCoord, target: &mut impl SpriteTarget) {
     let interval = 7.0 / 2.5;
     let time = (interval * explosion.upd).floor() as i16;
-    simple_draw(texture, fcoord, &Coord::new(time, 18), false);
+    simple_draw(target, fcoord, &Coord::new(time, 18), false);
 }
 
 pub fn set_area_explode(coord: &Coord, grid: &mut Grid, game_state: &mut GameState) {
     let tile = grid.get(coord);
     let upd = tile.upd + 2.5;
 
-    for y in -1..=1 {
-        for x in -1..=1 {
-            let curr_coord = coord.offset(&(x, y));
-            if x == 0 && y == 0 {
+    for curr_coord in grid.region(coord.offset(&(-1, -1)), coord.offset(&(1, 1))) {
+        if curr_coord == *coord {
+            let explosion1 = Tile {
+                typ: TileType::Explosion,
+                state: TileState::Dangerous,
+                mov: TileMove::None,
+                mov2: TileMove::None,
+                int: TileInteraction::None,
+                upd,
+            };
+            grid.set(&curr_coord, explosion1);
+            continue;
+        }
+
+        let tile = grid.get(&curr_coord);
+        match registry().get(tile.typ).on_explode(tile, game_state) {
+            Blast::Chain => {
+                let explosion2 = Tile {
+                    typ: TileType::Explosion2,
+                    state: TileState::Dangerous,
+                    mov: TileMove::None,
+                    mov2: TileMove::None,
+                    int: TileInteraction::None,
+                    upd,
+                };
+                grid.set(&curr_coord, explosion2);
+            }
+            Blast::Explode => {
                 let explosion1 = Tile {
                     typ: TileType::Explosion,
                     state: TileState::Dangerous,
@@ -53,45 +102,10 @@ pub fn set_area_explode(coord: &Coord, grid: &mut Grid, game_state: &mut GameSta
                     upd,
                 };
                 grid.set(&curr_coord, explosion1);
-                continue;
-            }
-
-            match grid.get(&curr_coord).typ {
-                TileType::Electron
-                | TileType::Murphy
-                | TileType::OrangeUtilityDisk
-                | TileType::SnikSnak
-                | TileType::YellowUtilityDisk => {
-                    if grid.get(&curr_coord).typ == TileType::Murphy {
-                        *game_state = GameState::Died;
-                    }
-                    let explosion2 = Tile {
-                        typ: TileType::Explosion2,
-                        state: TileState::Dangerous,
-                        mov: TileMove::None,
-                        mov2: TileMove::None,
-                        int: TileInteraction::None,
-                        upd,
-                    };
-                    grid.set(&curr_coord, explosion2);
-                }
-                TileType::Explosion2 => {}
-                _ => {
-                    if grid.get(&curr_coord).state != TileState::Indestructible {
-                        let explosion1 = Tile {
-                            typ: TileType::Explosion,
-                            state: TileState::Dangerous,
-                            mov: TileMove::None,
-                            mov2: TileMove::None,
-                            int: TileInteraction::None,
-                            upd,
-                        };
-                        grid.set(&curr_coord, explosion1);
-                    }
-                }
             }
-
-            //grid.set(&curr_coord, )
+            Blast::Keep => {}
         }
+
+        //grid.set(&curr_coord, )
     }
 }
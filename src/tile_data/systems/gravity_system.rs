@@ -1,11 +1,11 @@
 //let tile_down = grid.got(src.trans((0, 1))).map(|entity| entity.tile).unwrap_or_else(|| TileType::None);
 
-use macroquad::texture::Texture2D;
-
 use crate::{
     grid::{Coord, FCoord, Grid},
     level::LevelData,
     tile_data::{
+        behavior::TileBehavior,
+        sprite::SpriteTarget,
         tile::Tile,
         tile_interaction::TileInteraction,
         tile_move::TileMove,
@@ -17,18 +17,51 @@ use crate::{
 
 use super::explode_system::set_area_explode;
 
-pub fn gravity_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) {
+pub struct ZonkBehavior;
+
+impl TileBehavior for ZonkBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
+        gravity_system(coord, grid, level_data)
+    }
+
+    fn draw(&self, tile: &Tile, fcoord: &FCoord, mut target: &mut dyn SpriteTarget) {
+        draw_gravity_system(tile, fcoord, &mut target);
+    }
+}
+
+pub struct InfotronBehavior;
+
+impl TileBehavior for InfotronBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
+        gravity_system(coord, grid, level_data)
+    }
+
+    fn draw(&self, tile: &Tile, fcoord: &FCoord, mut target: &mut dyn SpriteTarget) {
+        draw_gravity_system(tile, fcoord, &mut target);
+    }
+
+    fn on_eaten(&self, level_data: &mut LevelData) {
+        level_data.infotrons_required -= 1;
+    }
+}
+
+/// Lets zonks and infotrons fall and roll off round tiles, returns where the tile went.
+pub fn gravity_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
     let tile = grid.get(coord);
 
     if tile.upd > 0.0 {
-        return;
+        return *coord;
     }
 
-    let coord_left = &coord.offset(&TileMove::Left);
     let coord_down = &coord.offset(&TileMove::Down);
-    let coord_right = &coord.offset(&TileMove::Right);
-    let coord_left_down = &coord.offset(&TileMove::Left).offset(&TileMove::Down);
-    let coord_right_down = &coord.offset(&TileMove::Right).offset(&TileMove::Down);
 
     match grid.get(coord_down).typ {
         TileType::Empty => {
@@ -36,38 +69,19 @@ pub fn gravity_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData
             let tile = Tile::mov_state(tile, upd, TileMove::Down, TileState::Dangerous);
             grid.set(coord_down, tile);
             grid.set(coord, Tile::transitory(upd, TileType::Empty));
+            return *coord_down;
         }
-        TileType::Infotron
-        | TileType::RAMChipsBase
-        | TileType::RAMChipsLeft
-        | TileType::RAMChipsRight
-        | TileType::RAMChipsUp
-        | TileType::RAMChipsDown
-        | TileType::Zonk => {
-            if grid.get(coord_left).typ == TileType::Empty
-                && grid.get(coord_left_down).typ == TileType::Empty
-            {
+        typ if is_round(typ) => match roll_direction(grid, coord) {
+            Some(mov) => {
+                let to = coord.offset(&mov);
                 let upd = tile.upd.move_update();
-                let tile = Tile::mov_state(tile, upd, TileMove::Left, TileState::Dangerous);
-                grid.set(coord_left, tile);
+                let tile = Tile::mov_state(tile, upd, mov, TileState::Dangerous);
+                grid.set(&to, tile);
                 grid.set(coord, Tile::transitory(upd, TileType::Empty));
-            } else if grid.get(coord_right).typ == TileType::Empty
-                && grid.get(coord_right_down).typ == TileType::Empty
-            {
-                let upd = tile.upd.move_update();
-                let tile = Tile::mov_state(tile, upd, TileMove::Right, TileState::Dangerous);
-                grid.set(coord_right, tile);
-                grid.set(coord, Tile::transitory(upd, TileType::Empty));
-            } else {
-                let tile = grid.get_mut_unchecked(coord);
-                tile.upd.dont_update();
-                tile.mov = TileMove::None;
-                tile.state = match tile.typ {
-                    TileType::Infotron => TileState::Eatable,
-                    _ => TileState::Moveable,
-                };
+                return to;
             }
-        }
+            None => grid.modify(coord, come_to_rest),
+        },
         TileType::Electron
         | TileType::Murphy
         | TileType::SnikSnak
@@ -78,18 +92,50 @@ pub fn gravity_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData
             }
         }
         _ => {
-            let tile = grid.get_mut_unchecked(coord);
-            tile.upd.dont_update();
-            tile.mov = TileMove::None;
-            tile.state = match tile.typ {
-                TileType::Infotron => TileState::Eatable,
-                _ => TileState::Moveable,
-            };
+            grid.modify(coord, come_to_rest);
         }
     }
+    *coord
+}
+
+/// Whether zonks and infotrons roll off this tile when they lie on it.
+pub const fn is_round(typ: TileType) -> bool {
+    matches!(
+        typ,
+        TileType::Infotron
+            | TileType::RAMChipsBase
+            | TileType::RAMChipsLeft
+            | TileType::RAMChipsRight
+            | TileType::RAMChipsUp
+            | TileType::RAMChipsDown
+            | TileType::Zonk
+    )
+}
+
+/// Which way a zonk or infotron at `coord` rolls off the round tile below it, left before
+/// right, both the side and the tile below the side must be empty.
+pub fn roll_direction(grid: &Grid, coord: &Coord) -> Option<TileMove> {
+    if !is_round(grid.get(&coord.offset(&TileMove::Down)).typ) {
+        return None;
+    }
+    [TileMove::Left, TileMove::Right].into_iter().find(|mov| {
+        let side = coord.offset(mov);
+        grid.get(&side).typ == TileType::Empty
+            && grid.get(&side.offset(&TileMove::Down)).typ == TileType::Empty
+    })
+}
+
+/// Stops a zonk or infotron that has nowhere to go.
+fn come_to_rest(tile: &mut Tile) {
+    tile.upd.dont_update();
+    tile.mov = TileMove::None;
+    tile.state = match tile.typ {
+        TileType::Infotron => TileState::Eatable,
+        _ => TileState::Moveable,
+    };
 }
 
-pub fn draw_gravity_system(tile: &Tile, fcoord: &FCoord, texture: Texture2D) {
+pub fn draw_gravity_system(tile: &Tile, fcoord: &FCoord, target: &mut impl SpriteTarget) {
     let smooth_dst = fcoord.offset_time(tile);
 
     let x = match tile.mov {
@@ -101,5 +147,5 @@ pub fn draw_gravity_system(tile: &Tile, fcoord: &FCoord, texture: Texture2D) {
     let mut coord = tile.typ.def_tex_pos();
     coord.x = x;
 
-    simple_draw(texture, &smooth_dst, &coord, false);
+    simple_draw(target, &smooth_dst, &coord, false);
 }
@@ -1,12 +1,11 @@
-use macroquad::{
-    prelude::{is_key_down, KeyCode, Vec2},
-    texture::Texture2D,
-};
+use macroquad::prelude::Vec2;
 
 use crate::{
     grid::{Coord, FCoord, Grid},
     level::{GameState, LevelData},
     tile_data::{
+        behavior::{registry, Blast, TileBehavior},
+        sprite::SpriteTarget,
         tile::Tile,
         tile_interaction::TileInteraction,
         tile_move::TileMove,
@@ -18,6 +17,34 @@ use crate::{
 
 use super::explode_system::set_area_explode;
 
+pub struct MurphyBehavior;
+
+impl TileBehavior for MurphyBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    /// Murphy follows the input, which can change at any tick.
+    fn always_active(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
+        let coord = murphy_system(coord, grid, level_data);
+        set_cam_pos(grid.get(&coord), &coord, level_data);
+        coord
+    }
+
+    fn draw(&self, tile: &Tile, fcoord: &FCoord, mut target: &mut dyn SpriteTarget) {
+        draw_murphy(tile, fcoord, &mut target);
+    }
+
+    fn on_explode(&self, _tile: &Tile, game_state: &mut GameState) -> Blast {
+        *game_state = GameState::Died;
+        Blast::Chain
+    }
+}
+
 pub fn murphy_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
     if let Some(tile) = grid.get_mut(coord) {
         if tile.upd > 0.0 {
@@ -25,7 +52,27 @@ pub fn murphy_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData)
         }
     }
 
-    for mov_input in &level_data.move_priority {
+    // with gravity Murphy falls through empty space and cannot climb it
+    let empty = |mov: TileMove| grid.neighbour(coord, &mov).typ == TileType::Empty;
+    if level_data.gravity_enabled && empty(TileMove::Down) {
+        let murphy = grid.get(coord);
+        let upd = murphy.upd.move_update();
+        let dir = murphy.mov2;
+        let coord2 = coord.offset(&TileMove::Down);
+        let murphy = Tile::murphy(upd, TileMove::Down, dir, TileInteraction::Eating(TileType::Empty));
+        grid.set(&coord2, murphy);
+        grid.set(coord, Tile::transitory(upd, TileType::Empty));
+        return coord2;
+    }
+
+    // on_eaten may change the level data, so the inputs are copied first
+    let move_priority: Vec<TileMove> = level_data
+        .move_priority
+        .iter()
+        .copied()
+        .filter(|mov| !(level_data.gravity_enabled && *mov == TileMove::Up && empty(TileMove::Up)))
+        .collect();
+    for mov_input in &move_priority {
         let murphy = grid.get(coord);
         let coord2 = coord.offset(mov_input);
         let tile2 = grid.get(&coord2);
@@ -33,12 +80,9 @@ pub fn murphy_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData)
             level_data.game_state = GameState::Finished;
         }
         if tile2.typ == TileType::Terminal {
-            for y in 0..grid.height {
-                for x in 0..grid.width {
-                    let special_coord = Coord::new(x, y);
-                    if grid.get(&special_coord).typ == TileType::YellowUtilityDisk {
-                        set_area_explode(&special_coord, grid, &mut level_data.game_state);
-                    }
+            for special_coord in grid.coords() {
+                if grid.get(&special_coord).typ == TileType::YellowUtilityDisk {
+                    set_area_explode(&special_coord, grid, &mut level_data.game_state);
                 }
             }
             return *coord;
@@ -55,12 +99,8 @@ pub fn murphy_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData)
                     _ => murphy.mov2,
                 };
                 let upd = murphy.upd.move_update();
-                if tile2.typ == TileType::Infotron {
-                    level_data.infotrons_required -= 1;
-                } else if tile2.typ == TileType::RedUtilityDisk {
-                    level_data.red_utility_disks += 1;
-                }
-                if is_key_down(KeyCode::Space) {
+                registry().get(tile2.typ).on_eaten(level_data);
+                if level_data.slurping {
                     let murphy = Tile::murphy(upd, *mov_input, dir, TileInteraction::Slurping);
                     grid.set(&coord2, Tile::transitory(upd, tile2.typ));
                     grid.set(coord, murphy);
@@ -74,18 +114,19 @@ pub fn murphy_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData)
                 }
             }
             TileState::Moveable => {
-                if mov_input == &TileMove::Left || mov_input == &TileMove::Right {
+                if registry().get(tile2.typ).on_pushed(tile2, *mov_input) {
                     let coord3 = coord2.offset(mov_input);
                     let tile3 = grid.get(&coord3);
                     if tile3.typ == TileType::Empty {
                         if murphy.upd > -1.0 {
-                            let murphy = grid.get_mut_unchecked(coord);
-                            murphy.int = TileInteraction::Pushing;
-                            murphy.mov = *mov_input;
-                            murphy.mov2 = match mov_input {
-                                TileMove::Right | TileMove::Left => *mov_input,
-                                _ => murphy.mov2,
-                            };
+                            grid.modify(coord, |murphy| {
+                                murphy.int = TileInteraction::Pushing;
+                                murphy.mov = *mov_input;
+                                murphy.mov2 = match mov_input {
+                                    TileMove::Right | TileMove::Left => *mov_input,
+                                    _ => murphy.mov2,
+                                };
+                            });
                             return *coord;
                         }
                         let dir = match mov_input {
@@ -104,29 +145,7 @@ pub fn murphy_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData)
             TileState::Tunnelable => {
                 let coord3 = coord2.offset(mov_input);
                 let tile3 = grid.get(&coord3);
-                if tile3.typ == TileType::Empty
-                    && matches!(
-                        (tile2.typ, mov_input),
-                        (TileType::PortsAll, _)
-                            | (TileType::PortsAllBlue, _)
-                            | (TileType::PortsDown, TileMove::Down)
-                            | (TileType::PortsDownBlue, TileMove::Down)
-                            | (TileType::PortsHorizontal, TileMove::Right)
-                            | (TileType::PortsHorizontal, TileMove::Left)
-                            | (TileType::PortsHorizontalBlue, TileMove::Right)
-                            | (TileType::PortsHorizontalBlue, TileMove::Left)
-                            | (TileType::PortsLeft, TileMove::Left)
-                            | (TileType::PortsLeftBlue, TileMove::Left)
-                            | (TileType::PortsRight, TileMove::Right)
-                            | (TileType::PortsRightBlue, TileMove::Right)
-                            | (TileType::PortsUp, TileMove::Up)
-                            | (TileType::PortsUpBlue, TileMove::Up)
-                            | (TileType::PortsVertical, TileMove::Up)
-                            | (TileType::PortsVertical, TileMove::Down)
-                            | (TileType::PortsVerticalBlue, TileMove::Up)
-                            | (TileType::PortsVerticalBlue, TileMove::Down)
-                    )
-                {
+                if tile3.typ == TileType::Empty && tile2.typ.port_allows(*mov_input) {
                     let dir = match mov_input {
                         TileMove::Right | TileMove::Left => *mov_input,
                         _ => murphy.mov2,
@@ -161,19 +180,19 @@ pub fn set_cam_pos(murphy: &Tile, coord: &Coord, level_data: &mut LevelData) {
     level_data.camera_target = Some(pos * 16.0 + 8.0);
 }
 
-pub fn draw_murphy(murphy: &Tile, fcoord: &FCoord, texture: Texture2D) {
+pub fn draw_murphy(murphy: &Tile, fcoord: &FCoord, target: &mut impl SpriteTarget) {
     let flip_x = matches!(&murphy.mov2, TileMove::Left);
 
     match murphy.int {
         TileInteraction::Eating(tile) => {
             let smooth_dst = &fcoord.offset_time(murphy);
             let anim_pos = 13 - (murphy.upd.max(0.0) * 4.0f32).floor() as i16;
-            simple_draw(texture, fcoord, &tile.def_tex_pos(), false);
-            simple_draw(texture, smooth_dst, &Coord::new(anim_pos, 7), flip_x);
+            simple_draw(target, fcoord, &tile.def_tex_pos(), false);
+            simple_draw(target, smooth_dst, &Coord::new(anim_pos, 7), flip_x);
         }
         TileInteraction::Pushing => {
             let smooth_dst = &fcoord.offset_time(murphy);
-            simple_draw(texture, smooth_dst, &Coord::new(3, 7), flip_x);
+            simple_draw(target, smooth_dst, &Coord::new(3, 7), flip_x);
         }
         TileInteraction::Slurping => {
             let pos = match &murphy.mov {
@@ -181,17 +200,17 @@ pub fn draw_murphy(murphy: &Tile, fcoord: &FCoord, texture: Texture2D) {
                 TileMove::Down => Coord::new(5, 7),
                 _ => Coord::new(6, 7),
             };
-            simple_draw(texture, fcoord, &pos, flip_x);
+            simple_draw(target, fcoord, &pos, flip_x);
         }
         TileInteraction::Tunneling(port) => {
             let smooth_dst = &fcoord.offset_time(murphy);
             let dst2 = &fcoord.offset(&murphy.mov.opposite());
             let smooth_dst3 = &dst2.offset_time(murphy);
             let anim_pos = 13 - (murphy.upd.max(0.0) * 4.0f32).floor() as i16;
-            simple_draw(texture, smooth_dst, &Coord::new(anim_pos, 7), flip_x);
-            simple_draw(texture, smooth_dst3, &Coord::new(anim_pos, 7), flip_x);
-            simple_draw(texture, dst2, &port.def_tex_pos(), false);
+            simple_draw(target, smooth_dst, &Coord::new(anim_pos, 7), flip_x);
+            simple_draw(target, smooth_dst3, &Coord::new(anim_pos, 7), flip_x);
+            simple_draw(target, dst2, &port.def_tex_pos(), false);
         }
-        _ => simple_draw(texture, fcoord, &Coord::new(0, 7), false),
+        _ => simple_draw(target, fcoord, &Coord::new(0, 7), false),
     }
 }
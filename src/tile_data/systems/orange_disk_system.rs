@@ -1,18 +1,43 @@
 use crate::{
-    grid::{Coord, Grid},
-    level::LevelData,
+    grid::{Coord, FCoord, Grid},
+    level::{GameState, LevelData},
     tile_data::{
+        behavior::{Blast, TileBehavior},
+        sprite::SpriteTarget,
         tile::Tile, tile_move::TileMove, tile_state::TileState, tile_type::TileType,
+        tile_type::simple_draw,
         tile_update::Updateable,
     },
 };
 
 use super::explode_system::set_area_explode;
 
-pub fn orange_disk_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) {
+pub struct OrangeDiskBehavior;
+
+impl TileBehavior for OrangeDiskBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
+        orange_disk_system(coord, grid, level_data)
+    }
+
+    fn draw(&self, tile: &Tile, fcoord: &FCoord, mut target: &mut dyn SpriteTarget) {
+        let fcoord = fcoord.offset_time(tile);
+        simple_draw(&mut target, &fcoord, &tile.typ.def_tex_pos(), false);
+    }
+
+    fn on_explode(&self, _tile: &Tile, _game_state: &mut GameState) -> Blast {
+        Blast::Chain
+    }
+}
+
+/// Drops the disk and explodes it when it lands, returns where it went.
+pub fn orange_disk_system(coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
     let disk = grid.get(coord);
     if disk.upd > 0.0 {
-        return;
+        return *coord;
     }
 
     let coord_down = coord.offset(&TileMove::Down);
@@ -22,9 +47,11 @@ pub fn orange_disk_system(coord: &Coord, grid: &mut Grid, level_data: &mut Level
         let tile = Tile::mov_state(disk, upd, TileMove::Down, TileState::Dangerous);
         grid.set(&coord_down, tile);
         grid.set(coord, Tile::transitory(upd, TileType::Empty));
+        return coord_down;
     } else if disk.mov == TileMove::Down && grid.get(&coord_down).mov == TileMove::None {
         set_area_explode(coord, grid, &mut level_data.game_state);
     } else {
-        grid.get_mut_unchecked(coord).upd.dont_update();
+        grid.modify(coord, |disk| disk.upd.dont_update());
     }
+    *coord
 }
@@ -1,6 +1,21 @@
-use macroquad::texture::Texture2D;
+use crate::{grid::{Grid, Coord, FCoord}, tile_data::{behavior::TileBehavior, sprite::SpriteTarget, tile::Tile, tile_type::{TileType, simple_draw}, tile_interaction::TileInteraction}, level::LevelData};
 
-use crate::{grid::{Grid, Coord, FCoord}, tile_data::{tile::Tile, tile_type::{TileType, simple_draw}, tile_interaction::TileInteraction}, level::LevelData};
+pub struct TransitoryBehavior;
+
+impl TileBehavior for TransitoryBehavior {
+    fn animated(&self) -> bool {
+        true
+    }
+
+    fn update(&self, coord: &Coord, grid: &mut Grid, level_data: &mut LevelData) -> Coord {
+        transitory_system(coord, grid, level_data);
+        *coord
+    }
+
+    fn draw(&self, tile: &Tile, fcoord: &FCoord, mut target: &mut dyn SpriteTarget) {
+        draw_transitory(tile, fcoord, &mut target);
+    }
+}
 
 pub fn transitory_system(coord: &Coord, grid: &mut Grid, _level_data: &mut LevelData) {
     if let Some(tile) = grid.get_mut(coord) {
@@ -11,7 +26,7 @@ pub fn transitory_system(coord: &Coord, grid: &mut Grid, _level_data: &mut Level
     }
 }
 
-pub fn draw_transitory(tile: &Tile, fcoord: &FCoord, texture: Texture2D) {
+pub fn draw_transitory(tile: &Tile, fcoord: &FCoord, target: &mut impl SpriteTarget) {
     let (y, offset) = match &tile.int {
         TileInteraction::Eating(tile) => match tile {
             TileType::Base => (0, 0),
@@ -26,7 +41,7 @@ pub fn draw_transitory(tile: &Tile, fcoord: &FCoord, texture: Texture2D) {
 
     let pos = Coord::new(x, y);
 
-    simple_draw(texture, fcoord, &pos, false);
+    simple_draw(target, fcoord, &pos, false);
 }
 
 
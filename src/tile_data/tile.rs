@@ -1,21 +1,18 @@
-use macroquad::texture::Texture2D;
+use std::hash::{Hash, Hasher};
 
 use crate::grid::FCoord;
 
 use super::{
-    systems::{
-        ai_system::draw_ai_system, explode_system::draw_explode_system,
-        gravity_system::draw_gravity_system, murphy_system::draw_murphy,
-        transitory_system::draw_transitory,
-    },
+    behavior::registry,
+    sprite::SpriteTarget,
     tile_interaction::TileInteraction,
     tile_move::TileMove,
     tile_state::TileState,
-    tile_type::{simple_draw, TileType},
+    tile_type::TileType,
     tile_update::{TileUpdate, Updateable},
 };
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Tile {
     pub typ: TileType,
     pub state: TileState,
@@ -26,41 +23,23 @@ pub struct Tile {
 }
 
 impl Tile {
+    /// Whether both tiles look and act alike, however far their timers have run.
+    pub fn same_state(&self, other: &Tile) -> bool {
+        self.typ == other.typ
+            && self.state == other.state
+            && self.mov == other.mov
+            && self.mov2 == other.mov2
+            && self.int == other.int
+    }
+
     pub fn update_time(&mut self, time: f32) {
-        if let TileType::Bug
-        | TileType::Electron
-        | TileType::Infotron
-        | TileType::Murphy
-        | TileType::OrangeUtilityDisk
-        | TileType::RedUtilityDisk
-        | TileType::Terminal
-        | TileType::Transitory
-        | TileType::YellowUtilityDisk
-        | TileType::SnikSnak
-        | TileType::Zonk
-        | TileType::Explosion
-        | TileType::Explosion2 = self.typ
-        {
+        if registry().get(self.typ).animated() {
             self.upd.normal_update(time);
         }
     }
 
-    pub fn draw(&self, fcoord: &FCoord, texture: Texture2D) {
-        match self.typ {
-            TileType::Murphy => draw_murphy(self, fcoord, texture),
-            TileType::SnikSnak => draw_ai_system(self, fcoord, texture),
-            TileType::Transitory => draw_transitory(self, fcoord, texture),
-            TileType::Infotron | TileType::Zonk => draw_gravity_system(self, fcoord, texture),
-            TileType::Explosion | TileType::Explosion2 => {
-                draw_explode_system(self, fcoord, texture)
-            }
-            TileType::OrangeUtilityDisk | TileType::YellowUtilityDisk => {
-                let fcoord = fcoord.offset_time(self);
-                simple_draw(texture, &fcoord, &self.typ.def_tex_pos(), false);
-            }
-            TileType::None | TileType::Empty => {}
-            _ => simple_draw(texture, fcoord, &self.typ.def_tex_pos(), false),
-        }
+    pub fn draw(&self, fcoord: &FCoord, target: &mut impl SpriteTarget) {
+        registry().get(self.typ).draw(self, fcoord, target);
     }
 
     pub fn transitory(upd: TileUpdate, typ: TileType) -> Self {
@@ -118,6 +97,17 @@ impl Tile {
     }
 }
 
+impl Hash for Tile {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.typ.hash(state);
+        self.state.hash(state);
+        self.mov.hash(state);
+        self.mov2.hash(state);
+        self.int.hash(state);
+        self.upd.to_bits().hash(state);
+    }
+}
+
 impl Default for Tile {
     fn default() -> Self {
         Self {
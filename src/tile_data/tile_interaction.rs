@@ -1,6 +1,6 @@
 use super::tile_type::TileType;
 
-#[derive(Default, Clone, Copy, Eq, PartialEq)]
+#[derive(Default, Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub enum TileInteraction {
     #[default]
     None,
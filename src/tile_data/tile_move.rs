@@ -1,6 +1,6 @@
 use crate::grid::{ToCoord, Coord};
 
-#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash, Debug)]
 pub enum TileMove {
     Up,
     Right,
@@ -11,6 +11,9 @@ pub enum TileMove {
 }
 
 impl TileMove {
+    /// The four directions, clockwise from up.
+    pub const DIRECTIONS: [TileMove; 4] = [TileMove::Up, TileMove::Right, TileMove::Down, TileMove::Left];
+
     pub const fn rotate_left(&self) -> Self {
         match self {
             Self::Up => Self::Left,
@@ -1,4 +1,4 @@
-#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
 pub enum TileState {
     Eatable,
     Moveable,
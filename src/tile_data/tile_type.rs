@@ -1,70 +1,207 @@
 use macroquad::{
-    prelude::{Rect, Vec2, PURPLE, WHITE},
+    prelude::PURPLE,
     text::{draw_text_ex, TextParams},
-    texture::{draw_texture_ex, DrawTextureParams, Texture2D},
 };
 
 use crate::grid::{Coord, FCoord};
 
 use super::{
+    sprite::SpriteTarget,
     tile::Tile,
     tile_state::TileState,
     tile_update::TileUpdate, tile_move::TileMove,
 };
 
-#[derive(Default, Clone, Copy, Eq, PartialEq, Debug)]
+/// Declared in `index` order, saves, observations and netplay hashes depend on it.
+#[repr(u8)]
+#[derive(Default, Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub enum TileType {
-    Base,
-    Bug,
-    Electron,
     Empty,
-    Exit,
-    HardwareBlueLight,
-    HardwareCapacitor,
-    HardwareGreenLight,
-    HardwareRedLight,
-    HardwareResistorsColored,
-    HardwareResistorsRed,
-    HardwareResistorsSpecial1,
-    HardwareResistorsSpecial2,
-    HardwareResistorsYellow,
-    HardwareWall,
-    HardwareYellowBlack,
-    Infotron,
+    Zonk,
+    Base,
     Murphy,
-    #[default]
-    None,
+    Infotron,
+    RAMChipsBase,
+    HardwareWall,
+    Exit,
     OrangeUtilityDisk,
-    PortsAll,
-    PortsAllBlue,
+    PortsRight,
     PortsDown,
-    PortsDownBlue,
-    PortsHorizontal,
-    PortsHorizontalBlue,
     PortsLeft,
-    PortsLeftBlue,
-    PortsRight,
-    PortsRightBlue,
     PortsUp,
+    PortsRightBlue,
+    PortsDownBlue,
+    PortsLeftBlue,
     PortsUpBlue,
+    SnikSnak,
+    YellowUtilityDisk,
+    Terminal,
+    RedUtilityDisk,
     PortsVertical,
+    PortsHorizontal,
+    PortsAll,
     PortsVerticalBlue,
-    RAMChipsBase,
-    RAMChipsDown,
+    PortsHorizontalBlue,
+    PortsAllBlue,
+    Electron,
+    Bug,
     RAMChipsLeft,
     RAMChipsRight,
+    HardwareResistorsSpecial2,
+    HardwareGreenLight,
+    HardwareBlueLight,
+    HardwareRedLight,
+    HardwareYellowBlack,
+    HardwareResistorsSpecial1,
+    HardwareCapacitor,
+    HardwareResistorsColored,
+    HardwareResistorsRed,
+    HardwareResistorsYellow,
     RAMChipsUp,
-    RedUtilityDisk,
-    SnikSnak,
-    Terminal,
+    RAMChipsDown,
+    #[default]
+    None,
     Transitory,
-    YellowUtilityDisk,
-    Zonk,
     Explosion,
     Explosion2,
 }
 
+/// Where a type can be stored.
+#[derive(Clone, Copy)]
+enum Storage {
+    /// Level files have a byte for it.
+    File,
+    /// Only text levels can hold it, level files write it as the byte of its closest match.
+    Text,
+    /// Only exists while playing.
+    Play,
+}
+
+struct TypeInfo {
+    typ: TileType,
+    byte: u8,
+    char: char,
+    storage: Storage,
+}
+
+const fn info(typ: TileType, byte: u8, char: char, storage: Storage) -> TypeInfo {
+    TypeInfo {
+        typ,
+        byte,
+        char,
+        storage,
+    }
+}
+
+/// The byte and text level character of every type, indexed by `index`.
+const TYPES: [TypeInfo; TileType::COUNT] = [
+    info(TileType::Empty, 0, ' ', Storage::File),
+    info(TileType::Zonk, 1, 'O', Storage::File),
+    info(TileType::Base, 2, '.', Storage::File),
+    info(TileType::Murphy, 3, 'M', Storage::File),
+    info(TileType::Infotron, 4, '*', Storage::File),
+    info(TileType::RAMChipsBase, 5, 'R', Storage::File),
+    info(TileType::HardwareWall, 6, '#', Storage::File),
+    info(TileType::Exit, 7, 'E', Storage::File),
+    info(TileType::OrangeUtilityDisk, 8, 'o', Storage::File),
+    info(TileType::PortsRight, 9, '>', Storage::File),
+    info(TileType::PortsDown, 10, 'v', Storage::File),
+    info(TileType::PortsLeft, 11, '<', Storage::File),
+    info(TileType::PortsUp, 12, '^', Storage::File),
+    // the special ports of the original game, whose header entries switch gravity and
+    // freeze zonks or enemies, they were first read as a terminal, a red disk, a
+    // capacitor and an infotron
+    info(TileType::PortsRightBlue, 13, ')', Storage::File),
+    info(TileType::PortsDownBlue, 14, 'V', Storage::File),
+    info(TileType::PortsLeftBlue, 15, '(', Storage::File),
+    info(TileType::PortsUpBlue, 16, 'A', Storage::File),
+    info(TileType::SnikSnak, 17, 'S', Storage::File),
+    info(TileType::YellowUtilityDisk, 18, 'y', Storage::File),
+    info(TileType::Terminal, 19, 'T', Storage::File),
+    info(TileType::RedUtilityDisk, 20, 'r', Storage::File),
+    info(TileType::PortsVertical, 21, '|', Storage::File),
+    info(TileType::PortsHorizontal, 22, '-', Storage::File),
+    info(TileType::PortsAll, 23, '+', Storage::File),
+    info(TileType::PortsVerticalBlue, 21, '!', Storage::Text),
+    info(TileType::PortsHorizontalBlue, 22, '=', Storage::Text),
+    info(TileType::PortsAllBlue, 23, 'x', Storage::Text),
+    info(TileType::Electron, 24, 'e', Storage::File),
+    info(TileType::Bug, 25, 'B', Storage::File),
+    info(TileType::RAMChipsLeft, 26, '[', Storage::File),
+    info(TileType::RAMChipsRight, 27, ']', Storage::File),
+    info(TileType::HardwareResistorsSpecial2, 28, '9', Storage::File),
+    info(TileType::HardwareGreenLight, 29, '2', Storage::File),
+    info(TileType::HardwareBlueLight, 30, '3', Storage::File),
+    info(TileType::HardwareRedLight, 31, '1', Storage::File),
+    info(TileType::HardwareYellowBlack, 32, '4', Storage::File),
+    info(TileType::HardwareResistorsSpecial1, 33, '8', Storage::File),
+    info(TileType::HardwareCapacitor, 34, '0', Storage::File),
+    info(TileType::HardwareResistorsColored, 35, '7', Storage::File),
+    info(TileType::HardwareResistorsRed, 36, '6', Storage::File),
+    info(TileType::HardwareResistorsYellow, 37, '5', Storage::File),
+    info(TileType::RAMChipsUp, 38, 'n', Storage::File),
+    info(TileType::RAMChipsDown, 39, 'u', Storage::File),
+    info(TileType::None, 40, '~', Storage::File),
+    info(TileType::Transitory, 0, ' ', Storage::Play),
+    info(TileType::Explosion, 0, '%', Storage::Play),
+    info(TileType::Explosion2, 0, '%', Storage::Play),
+];
+
+// every entry sits at its own index, and the file types come in byte order
+const _: () = {
+    let mut i = 0;
+    let mut byte = 0;
+    while i < TYPES.len() {
+        assert!(TYPES[i].typ as usize == i);
+        if let Storage::File = TYPES[i].storage {
+            assert!(TYPES[i].byte == byte);
+            byte += 1;
+        }
+        i += 1;
+    }
+};
+
+/// The types stored as `storage`, in `TYPES` order.
+const fn types_of<const N: usize>(storage: Storage) -> [TileType; N] {
+    let mut types = [TileType::None; N];
+    let mut count = 0;
+    let mut i = 0;
+    while i < TYPES.len() {
+        if TYPES[i].storage as u8 == storage as u8 {
+            types[count] = TYPES[i].typ;
+            count += 1;
+        }
+        i += 1;
+    }
+    assert!(count == N);
+    types
+}
+
 impl TileType {
+    /// Every type with a byte in level files, indexed by that byte. These are the types the
+    /// editor offers.
+    pub const PALETTE: [TileType; 41] = types_of(Storage::File);
+
+    /// Ports that only text levels can hold, level files have no byte for them.
+    pub const TEXT_ONLY: [TileType; 3] = types_of(Storage::Text);
+
+    /// Number of distinct values returned by `index`.
+    pub const COUNT: usize = 47;
+
+    /// A distinct number below `COUNT` for every type, including the ones that only exist
+    /// while playing, unlike `to_u8` which follows level files.
+    pub const fn index(self) -> u8 {
+        self as u8
+    }
+
+    pub const fn from_index(i: u8) -> Option<Self> {
+        if (i as usize) < TYPES.len() {
+            Some(TYPES[i as usize].typ)
+        } else {
+            None
+        }
+    }
+
     pub const fn def_tex_pos(&self) -> Coord {
         match self {
             TileType::Base => Coord::new(0, 0),
@@ -117,51 +254,96 @@ impl TileType {
     }
 
     pub fn from_u8(i: u8) -> Self {
-        match i {
-            1 => TileType::Zonk,
-            2 => TileType::Base,
-            3 => TileType::Murphy,
-            4 => TileType::Infotron,
-            5 => TileType::RAMChipsBase,
-            6 => TileType::HardwareWall,
-            7 => TileType::Exit,
-            8 => TileType::OrangeUtilityDisk,
-            9 => TileType::PortsRight,
-            10 => TileType::PortsDown,
-            11 => TileType::PortsLeft,
-            12 => TileType::PortsUp,
-            13 => TileType::Terminal,
-            14 => TileType::RedUtilityDisk,
-            15 => TileType::HardwareCapacitor,
-            16 => TileType::Infotron,
-            17 => TileType::SnikSnak,
-            18 => TileType::YellowUtilityDisk,
-            19 => TileType::Terminal,
-            20 => TileType::RedUtilityDisk,
-            21 => TileType::PortsVertical,
-            22 => TileType::PortsHorizontal,
-            23 => TileType::PortsAll,
-            24 => TileType::Electron,
-            25 => TileType::Bug,
-            26 => TileType::RAMChipsLeft,
-            27 => TileType::RAMChipsRight,
-            28 => TileType::HardwareResistorsSpecial2,
-            29 => TileType::HardwareGreenLight,
-            30 => TileType::HardwareBlueLight,
-            31 => TileType::HardwareRedLight,
-            32 => TileType::HardwareYellowBlack,
-            33 => TileType::HardwareResistorsSpecial1,
-            34 => TileType::HardwareCapacitor,
-            35 => TileType::HardwareResistorsColored,
-            36 => TileType::HardwareResistorsRed,
-            37 => TileType::HardwareResistorsYellow,
-            38 => TileType::RAMChipsUp,
-            39 => TileType::RAMChipsDown,
-            40 => TileType::None,
-            _ => TileType::Empty,
+        Self::try_from_u8(i).unwrap_or(TileType::Empty)
+    }
+
+    pub const fn try_from_u8(i: u8) -> Option<Self> {
+        if (i as usize) < Self::PALETTE.len() {
+            Some(Self::PALETTE[i as usize])
+        } else {
+            None
         }
     }
 
+    /// Inverse of `from_u8`, types without a byte of their own are written as their closest match.
+    pub const fn to_u8(self) -> u8 {
+        TYPES[self as usize].byte
+    }
+
+    /// Character used for this type in text levels.
+    pub const fn to_char(self) -> char {
+        TYPES[self as usize].char
+    }
+
+    pub fn from_char(c: char) -> Option<Self> {
+        TYPES
+            .iter()
+            .find(|info| !matches!(info.storage, Storage::Play) && info.char == c)
+            .map(|info| info.typ)
+    }
+
+    pub const fn is_hardware(&self) -> bool {
+        matches!(
+            self,
+            TileType::HardwareWall
+                | TileType::HardwareRedLight
+                | TileType::HardwareGreenLight
+                | TileType::HardwareBlueLight
+                | TileType::HardwareYellowBlack
+                | TileType::HardwareResistorsYellow
+                | TileType::HardwareResistorsRed
+                | TileType::HardwareResistorsColored
+                | TileType::HardwareResistorsSpecial1
+                | TileType::HardwareResistorsSpecial2
+                | TileType::HardwareCapacitor
+        )
+    }
+
+    pub const fn is_port(&self) -> bool {
+        matches!(
+            self,
+            TileType::PortsAll
+                | TileType::PortsHorizontal
+                | TileType::PortsVertical
+                | TileType::PortsLeft
+                | TileType::PortsRight
+                | TileType::PortsUp
+                | TileType::PortsDown
+                | TileType::PortsAllBlue
+                | TileType::PortsHorizontalBlue
+                | TileType::PortsVerticalBlue
+                | TileType::PortsLeftBlue
+                | TileType::PortsRightBlue
+                | TileType::PortsUpBlue
+                | TileType::PortsDownBlue
+        )
+    }
+
+    /// Whether Murphy can pass through this port moving `mov`.
+    pub const fn port_allows(self, mov: TileMove) -> bool {
+        matches!(
+            (self, mov),
+            (TileType::PortsAll, _)
+                | (TileType::PortsAllBlue, _)
+                | (TileType::PortsDown, TileMove::Down)
+                | (TileType::PortsDownBlue, TileMove::Down)
+                | (TileType::PortsHorizontal, TileMove::Right)
+                | (TileType::PortsHorizontal, TileMove::Left)
+                | (TileType::PortsHorizontalBlue, TileMove::Right)
+                | (TileType::PortsHorizontalBlue, TileMove::Left)
+                | (TileType::PortsLeft, TileMove::Left)
+                | (TileType::PortsLeftBlue, TileMove::Left)
+                | (TileType::PortsRight, TileMove::Right)
+                | (TileType::PortsRightBlue, TileMove::Right)
+                | (TileType::PortsUp, TileMove::Up)
+                | (TileType::PortsUpBlue, TileMove::Up)
+                | (TileType::PortsVertical, TileMove::Up)
+                | (TileType::PortsVertical, TileMove::Down)
+                | (TileType::PortsVerticalBlue, TileMove::Up)
+                | (TileType::PortsVerticalBlue, TileMove::Down)
+        )
+    }
+
     pub fn to_tile(self) -> Tile {
         match self {
             TileType::Base
@@ -249,26 +431,8 @@ impl TileType {
     }
 }
 
-pub fn simple_draw(texture: Texture2D, fcoord: &FCoord, pos: &Coord, flip_x: bool) {
-    draw_texture_ex(
-        texture,
-        fcoord.x * 16.0,
-        fcoord.y * 16.0,
-        WHITE,
-        DrawTextureParams {
-            dest_size: Some(Vec2 { x: 16.0, y: 16.0 }),
-            source: Some(Rect {
-                x: pos.x as f32 * 17.0,
-                y: pos.y as f32 * 17.0,
-                w: 16.0,
-                h: 16.0,
-            }),
-            rotation: 0.0,
-            flip_x,
-            flip_y: false,
-            pivot: None,
-        },
-    );
+pub fn simple_draw(target: &mut impl SpriteTarget, fcoord: &FCoord, pos: &Coord, flip_x: bool) {
+    target.draw_sprite(fcoord, pos, flip_x, false);
 }
 
 pub fn draw_time(coord: &FCoord, time: TileUpdate) {
@@ -0,0 +1,242 @@
+use std::{
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use supaplex::{
+    grid::Coord,
+    level::{GameState, Level},
+    replay::{REPLAY_DELTA_TIME, REPLAY_TICKS_PER_STEP},
+    tile_data::{tile_move::TileMove, tile_type::TileType},
+};
+
+/// One tick of `REPLAY_DELTA_TIME` every 1/64 s moves Murphy 8 tiles per second, the same
+/// speed as the window at its default speed.
+const TICK_INTERVAL: Duration = Duration::from_micros(15_625);
+const TICKS_PER_FRAME: u32 = 2;
+/// Asking `stty` for the terminal size starts a process, so it is only done once a second.
+const SIZE_CHECK_TICKS: u32 = 64;
+
+enum Key {
+    Move(TileMove),
+    Slurp,
+    Restart,
+    Quit,
+}
+
+/// Puts the terminal in raw mode through `stty` and hides the cursor, both are restored when
+/// dropped, so also when the game stops with an error.
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        let raw_mode = Self {
+            saved: saved.trim().to_string(),
+        };
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[?25l\x1b[2J")?;
+        stdout.flush()?;
+        Ok(raw_mode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "\x1b[0m\x1b[?25h\x1b[2J\x1b[H");
+        let _ = stdout.flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("stty failed, is stdin a terminal?"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Rows and columns of the terminal, 24x80 when it cannot be asked.
+fn terminal_size() -> (i16, i16) {
+    stty(&["size"])
+        .ok()
+        .and_then(|size| {
+            let (rows, cols) = size.trim().split_once(' ')?;
+            Some((rows.parse().ok()?, cols.parse().ok()?))
+        })
+        .filter(|&(rows, cols)| rows > 0 && cols > 0)
+        .unwrap_or((24, 80))
+}
+
+/// Reads keys on a separate thread, since stdin cannot be polled without blocking.
+fn spawn_input() -> Receiver<Key> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut escape = 0;
+        for byte in io::stdin().lock().bytes() {
+            let Ok(byte) = byte else {
+                break;
+            };
+            // arrow keys arrive as ESC [ A..D
+            let key = match (escape, byte) {
+                (_, 0x1b) => {
+                    escape = 1;
+                    continue;
+                }
+                (1, b'[') => {
+                    escape = 2;
+                    continue;
+                }
+                (2, b'A') => Some(Key::Move(TileMove::Up)),
+                (2, b'B') => Some(Key::Move(TileMove::Down)),
+                (2, b'C') => Some(Key::Move(TileMove::Right)),
+                (2, b'D') => Some(Key::Move(TileMove::Left)),
+                (_, b' ') => Some(Key::Slurp),
+                (_, b'r') => Some(Key::Restart),
+                (_, b'q') | (_, 3) => Some(Key::Quit),
+                _ => None,
+            };
+            escape = 0;
+            if let Some(key) = key {
+                if sender.send(key).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    receiver
+}
+
+/// Plays `level` in the terminal with the same simulation as the window. Terminals only
+/// report presses, so every arrow key holds its direction for one tile step and key repeat
+/// keeps Murphy walking. Space makes the next move a slurp, r restarts and q quits.
+pub fn run_tui(level: &Level) -> io::Result<()> {
+    let _raw_mode = RawMode::enable()?;
+    let input = spawn_input();
+    let mut stdout = io::stdout().lock();
+
+    let mut current = level.clone();
+    let mut held_ticks = 0;
+    let mut slurp_next = false;
+    let mut ticks: u32 = 0;
+    let mut size = terminal_size();
+    let mut next_tick = Instant::now();
+
+    'game: loop {
+        while let Ok(key) = input.try_recv() {
+            match key {
+                Key::Move(mov) => {
+                    current.data.move_priority = vec![mov];
+                    current.data.slurping = slurp_next;
+                    slurp_next = false;
+                    held_ticks = REPLAY_TICKS_PER_STEP;
+                }
+                Key::Slurp => slurp_next = true,
+                Key::Restart => {
+                    current = level.clone();
+                    held_ticks = 0;
+                }
+                Key::Quit => break 'game,
+            }
+        }
+
+        if held_ticks == 0 {
+            current.data.move_priority.clear();
+            current.data.slurping = false;
+        } else {
+            held_ticks -= 1;
+        }
+        current.tick(REPLAY_DELTA_TIME);
+
+        ticks += 1;
+        if ticks.is_multiple_of(SIZE_CHECK_TICKS) {
+            size = terminal_size();
+        }
+        if ticks.is_multiple_of(TICKS_PER_FRAME) {
+            draw(&mut stdout, &current, size)?;
+        }
+
+        next_tick += TICK_INTERVAL;
+        thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+
+    Ok(())
+}
+
+fn draw(out: &mut impl Write, level: &Level, (rows, cols): (i16, i16)) -> io::Result<()> {
+    let grid = &level.grid;
+    let view_width = grid.width.min(cols).max(1);
+    let view_height = grid.height.min(rows - 1).max(1);
+
+    // keep Murphy in the middle of the view when the level does not fit
+    let murphy = grid.find(TileType::Murphy).unwrap_or(Coord::new(0, 0));
+    let left = (murphy.x - view_width / 2).clamp(0, grid.width - view_width);
+    let top = (murphy.y - view_height / 2).clamp(0, grid.height - view_height);
+
+    let mut frame = String::from("\x1b[H");
+    for y in top..top + view_height {
+        let mut color = "";
+        for x in left..left + view_width {
+            let typ = grid.get(&Coord::new(x, y)).typ;
+            let tile_color = tile_color(typ);
+            if tile_color != color {
+                frame += tile_color;
+                color = tile_color;
+            }
+            frame.push(typ.to_char());
+        }
+        frame += "\x1b[0m\x1b[K\r\n";
+    }
+
+    let status = match level.data.game_state {
+        GameState::Active => "arrows move, space slurp, r restart, q quit",
+        GameState::Died => "you died, r to restart",
+        GameState::Finished => "level finished!",
+    };
+    frame += &format!(
+        "{}  infotrons: {}  red disks: {}  {status}\x1b[K",
+        level.data.name.trim(),
+        level.data.infotrons_required.max(0),
+        level.data.red_utility_disks,
+    );
+
+    out.write_all(frame.as_bytes())?;
+    out.flush()
+}
+
+/// ANSI colour escape for each type, roughly matching the sprites.
+const fn tile_color(typ: TileType) -> &'static str {
+    match typ {
+        TileType::Murphy => "\x1b[0;1;31m",
+        TileType::Infotron => "\x1b[0;1;32m",
+        TileType::Base => "\x1b[0;32m",
+        TileType::Zonk => "\x1b[0;37m",
+        TileType::Exit => "\x1b[0;1;33m",
+        TileType::SnikSnak => "\x1b[0;1;35m",
+        TileType::Electron => "\x1b[0;1;36m",
+        TileType::Bug => "\x1b[0;36m",
+        TileType::Terminal => "\x1b[0;34m",
+        TileType::OrangeUtilityDisk | TileType::YellowUtilityDisk => "\x1b[0;33m",
+        TileType::RedUtilityDisk => "\x1b[0;31m",
+        TileType::Explosion | TileType::Explosion2 => "\x1b[0;1;33m",
+        TileType::RAMChipsBase
+        | TileType::RAMChipsLeft
+        | TileType::RAMChipsRight
+        | TileType::RAMChipsUp
+        | TileType::RAMChipsDown => "\x1b[0;32;100m",
+        _ if typ.is_hardware() => "\x1b[0;90m",
+        _ if typ.is_port() => "\x1b[0;33;100m",
+        _ => "\x1b[0m",
+    }
+}